@@ -114,6 +114,27 @@ fn build_apps_and_pack_fs() {
             fs_target_dir.display()
         )
     });
+    create_swap_image(&fs_target_dir).unwrap_or_else(|err| {
+        panic!(
+            "failed to create swap image in {}: {err}",
+            fs_target_dir.display()
+        )
+    });
+}
+
+/// 交换区镜像的大小，与内核 `swap::SWAP_SIZE` 一致
+const SWAP_SIZE: u64 = 32 << 20;
+
+/// 生成交换设备使用的空白磁盘镜像 `swap.img`。
+fn create_swap_image(fs_target: &PathBuf) -> std::io::Result<()> {
+    let swap_file = fs_target.join("swap.img");
+    println!("cargo:rerun-if-changed={}", swap_file.display());
+    let f = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(swap_file)?;
+    f.set_len(SWAP_SIZE)
 }
 
 fn build_user_app(tg_user_root: &PathBuf, name: &str, base_address: u64) {
@@ -38,6 +38,8 @@ timeout 5 qemu-system-riscv64 \
     -bios none \
     -drive file="target/riscv64gc-unknown-none-elf/debug/fs.img",if=none,format=raw,id=x0 \
     -device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0 \
+    -drive file="target/riscv64gc-unknown-none-elf/debug/swap.img",if=none,format=raw,id=x1 \
+    -device virtio-blk-device,drive=x1,bus=virtio-mmio-bus.1 \
     -kernel "target/riscv64gc-unknown-none-elf/debug/tg-ch18" \
     -nographic
 
@@ -0,0 +1,116 @@
+//! 设备树（FDT）解析。
+//!
+//! 只取出内核用到的几项：时钟频率和 Goldfish RTC 的地址。
+//! 格式见 <https://devicetree-specification.readthedocs.io/en/stable/flattened-format.html>。
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+const FDT_END: u32 = 9;
+
+/// 从设备树中得到的机器信息。
+#[derive(Default, Debug)]
+pub struct MachineInfo {
+    /// `/cpus/timebase-frequency`，`time` 寄存器每秒的计数
+    pub timebase_frequency: Option<usize>,
+    /// `google,goldfish-rtc` 设备的 MMIO 基址
+    pub goldfish_rtc: Option<usize>,
+}
+
+/// 解析 `dtb` 处的设备树，地址无效时返回 `None`。
+///
+/// # Safety
+///
+/// `dtb` 为 0 或指向一段可读的设备树。
+pub unsafe fn parse(dtb: usize) -> Option<MachineInfo> {
+    if dtb == 0 || dtb % 4 != 0 || be32(dtb) != FDT_MAGIC {
+        return None;
+    }
+    let structs = dtb + be32(dtb + 8) as usize;
+    let strings = dtb + be32(dtb + 12) as usize;
+    let mut info = MachineInfo::default();
+    let mut depth = 0;
+    // 当前节点是否为 /cpus、是否为 Goldfish RTC，以及它的 reg 属性
+    let mut in_cpus = false;
+    let mut is_rtc = false;
+    let mut reg = None;
+    let mut p = structs;
+    loop {
+        let token = be32(p);
+        p += 4;
+        match token {
+            FDT_BEGIN_NODE => {
+                let name = cstr(p);
+                p = align4(p + name.len() + 1);
+                depth += 1;
+                if depth == 2 {
+                    in_cpus = name == b"cpus";
+                }
+                is_rtc = false;
+                reg = None;
+            }
+            FDT_END_NODE => {
+                if is_rtc {
+                    info.goldfish_rtc = info.goldfish_rtc.or(reg);
+                }
+                is_rtc = false;
+                depth -= 1;
+            }
+            FDT_PROP => {
+                let len = be32(p) as usize;
+                let name = cstr(strings + be32(p + 4) as usize);
+                let value = p + 8;
+                p = align4(value + len);
+                match name {
+                    // 也可能写在 /cpus 下的每个 cpu 节点中
+                    b"timebase-frequency" if in_cpus => {
+                        info.timebase_frequency.get_or_insert(cells(value, len));
+                    }
+                    b"compatible" => {
+                        let value = core::slice::from_raw_parts(value as *const u8, len);
+                        is_rtc = value
+                            .split(|&c| c == 0)
+                            .any(|compatible| compatible == b"google,goldfish-rtc");
+                    }
+                    // QEMU virt 的 #address-cells 为 2，只取第一个地址
+                    b"reg" if len >= 8 => reg = Some(cells(value, 8)),
+                    _ => {}
+                }
+            }
+            FDT_NOP => {}
+            FDT_END => break,
+            _ => return None,
+        }
+    }
+    Some(info)
+}
+
+#[inline]
+unsafe fn be32(addr: usize) -> u32 {
+    u32::from_be(core::ptr::read(addr as *const u32))
+}
+
+/// 读取 1 个或 2 个 cell 组成的数
+unsafe fn cells(addr: usize, len: usize) -> usize {
+    if len >= 8 {
+        (be32(addr) as usize) << 32 | be32(addr + 4) as usize
+    } else {
+        be32(addr) as usize
+    }
+}
+
+unsafe fn cstr(addr: usize) -> &'static [u8] {
+    let start = addr as *const u8;
+    let mut len = 0;
+    while *start.add(len) != 0 {
+        len += 1;
+    }
+    core::slice::from_raw_parts(start, len)
+}
+
+#[inline]
+fn align4(addr: usize) -> usize {
+    (addr + 3) & !3
+}
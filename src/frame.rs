@@ -0,0 +1,244 @@
+//! 物理页帧分配器。
+//!
+//! 内核镜像之后的物理内存中，前 [`KERNEL_HEAP_SIZE`] 字节交给内核堆，其余按页帧管理：
+//! 用户页、页表页和 virtio 的 DMA 缓冲区都从这里分配，用户内存耗尽不会拖垮内核堆。
+//! 空闲页帧记录在位图中，每个页帧另有引用计数和用途，写时复制共享的页帧靠引用计数回收；
+//! 从交换区换入的页帧还记录其在交换区中的槽位，页帧释放时一并释放槽位。
+//! 内核恒等映射全部物理内存，页帧的物理地址可以直接访问。
+
+use crate::{swap, Sv39};
+use alloc::{vec, vec::Vec};
+use core::{fmt, ops::Range};
+use spin::Mutex;
+use tg_console::log;
+use tg_kernel_vm::page_table::MmuMeta;
+
+/// 内核堆的大小，内核镜像之后剩余的物理内存都作为页帧
+pub const KERNEL_HEAP_SIZE: usize = 16 << 20;
+
+const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+
+/// 页帧的用途。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrameKind {
+    /// 空闲
+    Free,
+    /// 用户页
+    User,
+    /// 页表页
+    PageTable,
+    /// 设备 DMA 缓冲区
+    Dma,
+}
+
+/// 页帧使用情况，单位为页。
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrameStats {
+    /// 管理的页帧总数
+    pub total: usize,
+    /// 空闲页帧数
+    pub free: usize,
+    /// 用户页
+    pub user: usize,
+    /// 页表页
+    pub page_table: usize,
+    /// DMA 缓冲区
+    pub dma: usize,
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frames: {}/{} free, user {}, page table {}, dma {}",
+            self.free, self.total, self.user, self.page_table, self.dma
+        )
+    }
+}
+
+impl FrameStats {
+    fn count_mut(&mut self, kind: FrameKind) -> &mut usize {
+        match kind {
+            FrameKind::Free => &mut self.free,
+            FrameKind::User => &mut self.user,
+            FrameKind::PageTable => &mut self.page_table,
+            FrameKind::Dma => &mut self.dma,
+        }
+    }
+}
+
+/// 每个页帧的元数据。
+#[derive(Clone, Copy)]
+struct Frame {
+    /// 引用计数，空闲页帧为 0
+    refs: usize,
+    kind: FrameKind,
+    /// 换入的页在交换区中仍然保留的槽位
+    swap_slot: Option<usize>,
+}
+
+struct FrameAllocator {
+    /// 第一个页帧的页号
+    base: usize,
+    /// 置位表示页帧已分配，末尾不足一个字的部分也置位
+    bitmap: Vec<u64>,
+    frames: Vec<Frame>,
+    /// 下次从这个页帧开始查找
+    next: usize,
+    stats: FrameStats,
+}
+
+impl FrameAllocator {
+    /// 在 `[from, to)` 中查找 `count` 个连续的空闲页帧，返回第一个页帧的序号。
+    fn find(&self, from: usize, to: usize, count: usize) -> Option<usize> {
+        let mut run = 0;
+        let mut i = from;
+        while i < to {
+            // 跳过全部已分配的字
+            if run == 0 && i % 64 == 0 && self.bitmap[i / 64] == !0 {
+                i += 64;
+                continue;
+            }
+            if self.bitmap[i / 64] & (1 << (i % 64)) != 0 {
+                run = 0;
+            } else {
+                run += 1;
+                if run == count {
+                    return Some(i + 1 - count);
+                }
+            }
+            i += 1;
+        }
+        None
+    }
+
+    fn alloc(&mut self, count: usize, kind: FrameKind) -> Option<usize> {
+        let total = self.frames.len();
+        let first = self
+            .find(self.next, total, count)
+            .or_else(|| self.find(0, total, count))?;
+        for i in first..first + count {
+            self.bitmap[i / 64] |= 1 << (i % 64);
+            self.frames[i] = Frame {
+                refs: 1,
+                kind,
+                swap_slot: None,
+            };
+        }
+        self.next = first + count;
+        self.stats.free -= count;
+        *self.stats.count_mut(kind) += count;
+        Some(self.base + first)
+    }
+
+    /// 页号 `ppn` 的页帧序号，不由本分配器管理时返回 `None`。
+    fn index(&self, ppn: usize) -> Option<usize> {
+        ppn.checked_sub(self.base).filter(|&i| i < self.frames.len())
+    }
+
+    /// 减少引用，返回释放的页帧数和其中需要释放的交换区槽位。
+    fn release(&mut self, ppn: usize, count: usize) -> (usize, Vec<usize>) {
+        let mut freed = 0;
+        let mut slots = Vec::new();
+        for ppn in ppn..ppn + count {
+            let Some(i) = self.index(ppn) else {
+                continue;
+            };
+            let frame = &mut self.frames[i];
+            assert!(frame.refs > 0, "free an unallocated frame {ppn:#x}");
+            frame.refs -= 1;
+            if frame.refs == 0 {
+                let kind = frame.kind;
+                frame.kind = FrameKind::Free;
+                slots.extend(frame.swap_slot.take());
+                self.bitmap[i / 64] &= !(1 << (i % 64));
+                *self.stats.count_mut(kind) -= 1;
+                self.stats.free += 1;
+                freed += 1;
+            }
+        }
+        (freed, slots)
+    }
+}
+
+static FRAMES: Mutex<Option<FrameAllocator>> = Mutex::new(None);
+
+/// 把物理地址范围 `memory` 中完整的页交给页帧分配器管理。
+pub fn init(memory: Range<usize>) {
+    let base = memory.start.div_ceil(PAGE_SIZE);
+    let total = (memory.end / PAGE_SIZE).saturating_sub(base);
+    let mut bitmap = vec![0u64; total.div_ceil(64)];
+    if total % 64 != 0 {
+        bitmap[total / 64] = !0 << (total % 64);
+    }
+    let stats = FrameStats {
+        total,
+        free: total,
+        ..Default::default()
+    };
+    log::info!(
+        "(frames) -> {:#10x}..{:#10x}, {total} frames",
+        base * PAGE_SIZE,
+        (base + total) * PAGE_SIZE,
+    );
+    *FRAMES.lock() = Some(FrameAllocator {
+        base,
+        bitmap,
+        frames: vec![
+            Frame {
+                refs: 0,
+                kind: FrameKind::Free,
+                swap_slot: None,
+            };
+            total
+        ],
+        next: 0,
+        stats,
+    });
+}
+
+/// 分配 `count` 个物理上连续、已清零的页帧，返回第一个页帧的页号，没有足够的连续页帧时返回 `None`。
+pub fn alloc(count: usize, kind: FrameKind) -> Option<usize> {
+    let ppn = FRAMES.lock().as_mut().unwrap().alloc(count, kind)?;
+    // SAFETY: 页帧刚分配，不被其他对象引用，且内核恒等映射了全部物理内存
+    unsafe { core::ptr::write_bytes((ppn * PAGE_SIZE) as *mut u8, 0, count * PAGE_SIZE) };
+    Some(ppn)
+}
+
+/// 增加页帧 `ppn` 的一个引用。
+pub fn share(ppn: usize) {
+    let mut frames = FRAMES.lock();
+    let frames = frames.as_mut().unwrap();
+    if let Some(i) = frames.index(ppn) {
+        frames.frames[i].refs += 1;
+    }
+}
+
+/// 页帧 `ppn` 的引用计数，不由分配器管理的页帧为 0。
+pub fn ref_count(ppn: usize) -> usize {
+    let frames = FRAMES.lock();
+    let frames = frames.as_ref().unwrap();
+    frames.index(ppn).map_or(0, |i| frames.frames[i].refs)
+}
+
+/// 减少从 `ppn` 开始的 `count` 个页帧的引用，释放不再被引用的页帧，返回释放的页帧数。
+pub fn dealloc(ppn: usize, count: usize) -> usize {
+    let (freed, slots) = FRAMES.lock().as_mut().unwrap().release(ppn, count);
+    for slot in slots {
+        swap::free_slot(slot);
+    }
+    freed
+}
+
+/// 设置页帧 `ppn` 在交换区中保留的槽位，返回原来的槽位。
+pub fn set_swap_slot(ppn: usize, slot: Option<usize>) -> Option<usize> {
+    let mut frames = FRAMES.lock();
+    let frames = frames.as_mut().unwrap();
+    let i = frames.index(ppn)?;
+    core::mem::replace(&mut frames.frames[i].swap_slot, slot)
+}
+
+/// 页帧使用情况。
+pub fn stats() -> FrameStats {
+    FRAMES.lock().as_ref().unwrap().stats
+}
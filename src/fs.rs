@@ -1,6 +1,7 @@
 use crate::virtio_block::BLOCK_DEVICE;
 use alloc::{string::String, sync::Arc, vec::Vec};
 use spin::Lazy;
+use tg_kernel_vm::PageSource;
 use tg_easy_fs::{
     EasyFileSystem, FSManager, FileHandle, Inode, OpenFlags, PipeReader, PipeWriter, UserBuffer,
 };
@@ -77,6 +78,30 @@ pub fn read_all(fd: Arc<FileHandle>) -> Vec<u8> {
     v
 }
 
+/// 文件映射的内容来源，第 `index` 页对应文件中 `offset + index * PAGE_SIZE` 处。
+pub struct FileSource {
+    /// 映射的文件
+    pub inode: Arc<Inode>,
+    /// 映射开始处在文件中的偏移
+    pub offset: usize,
+}
+
+impl PageSource for FileSource {
+    fn read_page(&self, index: usize, page: &mut [u8]) {
+        // 超出文件末尾的部分保持为 0
+        self.inode.read_at(self.offset + index * page.len(), page);
+    }
+
+    fn write_page(&self, index: usize, page: &[u8]) {
+        // 只写回文件范围内的部分，不改变文件大小
+        let offset = self.offset + index * page.len();
+        let size = self.inode.size() as usize;
+        if offset < size {
+            self.inode.write_at(offset, &page[..page.len().min(size - offset)]);
+        }
+    }
+}
+
 /// 统一的文件描述符类型
 #[derive(Clone)]
 pub enum Fd {
@@ -0,0 +1,104 @@
+//! futex 等待队列。
+//!
+//! 等待者按 [`FutexKey`] 分组：私有内存中的 futex 按 (进程, 虚拟地址) 区分，不受写时复制和换出的影响；
+//! 共享映射中的 futex 按物理地址区分，因此不同进程映射同一物理页时也能互相唤醒。
+//! 阻塞与唤醒沿用 `tg-sync` 的方式：这里只维护 `ThreadId` 队列，
+//! 实际的阻塞由 trap 循环调用 `block_current` 完成，唤醒通过 `wake_thread` 重新入队。
+
+use crate::processor::wake_thread;
+use alloc::collections::{BTreeMap, VecDeque};
+use spin::Mutex;
+use tg_task_manage::ThreadId;
+
+/// futex 等待队列的键。
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FutexKey {
+    /// 私有 futex 或私有映射中的 futex：(pid, 虚拟地址)
+    Private(usize, usize),
+    /// 共享映射中的 futex：物理地址。共享映射的页不做写时复制也不会换出，物理地址不变
+    Shared(usize),
+}
+
+/// 阻塞在某个 futex 上的线程。
+struct FutexWaiter {
+    tid: ThreadId,
+    /// `FUTEX_WAIT_BITSET` 指定的掩码，普通等待为全 1
+    bitset: u32,
+}
+
+/// 键 -> 等待队列。
+static FUTEX_QUEUES: Mutex<BTreeMap<FutexKey, VecDeque<FutexWaiter>>> = Mutex::new(BTreeMap::new());
+
+/// 将 `tid` 加入 `key` 对应的等待队列。
+pub fn wait(key: FutexKey, tid: ThreadId, bitset: u32) {
+    FUTEX_QUEUES
+        .lock()
+        .entry(key)
+        .or_default()
+        .push_back(FutexWaiter { tid, bitset });
+}
+
+/// 唤醒 `key` 上至多 `count` 个掩码与 `bitset` 相交的线程，返回唤醒的数量。
+pub fn wake(key: FutexKey, count: usize, bitset: u32) -> usize {
+    let mut queues = FUTEX_QUEUES.lock();
+    let mut woken = 0;
+    if let Some(queue) = queues.get_mut(&key) {
+        let mut i = 0;
+        while i < queue.len() && woken < count {
+            if queue[i].bitset & bitset != 0 {
+                let waiter = queue.remove(i).unwrap();
+                if wake_thread(waiter.tid) {
+                    woken += 1;
+                }
+            } else {
+                i += 1;
+            }
+        }
+        if queue.is_empty() {
+            queues.remove(&key);
+        }
+    }
+    woken
+}
+
+/// 唤醒 `key` 上至多 `wake_count` 个线程，再把至多 `requeue_count` 个剩余线程移到 `key2` 上。
+///
+/// 返回 (唤醒数量, 转移数量)。
+pub fn requeue(key: FutexKey, key2: FutexKey, wake_count: usize, requeue_count: usize) -> (usize, usize) {
+    let woken = wake(key, wake_count, u32::MAX);
+    let mut queues = FUTEX_QUEUES.lock();
+    let mut moved = VecDeque::new();
+    if let Some(queue) = queues.get_mut(&key) {
+        while moved.len() < requeue_count {
+            match queue.pop_front() {
+                Some(waiter) => moved.push_back(waiter),
+                None => break,
+            }
+        }
+        if queue.is_empty() {
+            queues.remove(&key);
+        }
+    }
+    let requeued = moved.len();
+    if requeued != 0 {
+        queues.entry(key2).or_default().append(&mut moved);
+    }
+    (woken, requeued)
+}
+
+/// 等待超时，将 `tid` 从所在的等待队列中移出（它可能已经被 REQUEUE 到别的地址上）。
+///
+/// 线程已经被唤醒时返回 false。
+pub fn cancel_wait(tid: ThreadId) -> bool {
+    let mut queues = FUTEX_QUEUES.lock();
+    let mut found = false;
+    queues.retain(|_, queue| {
+        queue.retain(|waiter| {
+            let hit = waiter.tid == tid;
+            found |= hit;
+            !hit
+        });
+        !queue.is_empty()
+    });
+    found
+}
@@ -6,9 +6,17 @@
 #![cfg_attr(target_arch = "riscv64", deny(warnings, missing_docs))]
 #![cfg_attr(not(target_arch = "riscv64"), allow(dead_code, unused_imports))]
 
+mod frame;
 mod fs;
+mod dtb;
+mod futex;
 mod process;
 mod processor;
+mod rtc;
+mod sleep;
+mod swap;
+mod timer;
+mod user_stack;
 mod virtio_block;
 
 #[macro_use]
@@ -20,8 +28,8 @@ extern crate alloc;
 use crate::{
     fs::{read_all, FS},
     impls::{Sv39Manager, SyscallContext},
-    process::Process,
-    processor::{ProcManager, ProcessorInner, ThreadManager},
+    process::{wait_status, Process},
+    processor::{block_current, ProcManager, ProcessorInner, ThreadManager},
 };
 use alloc::alloc::alloc;
 use core::{alloc::Layout, cell::UnsafeCell, mem::MaybeUninit};
@@ -40,7 +48,7 @@ use tg_kernel_vm::{
     AddressSpace,
 };
 use tg_sbi;
-use tg_signal::SignalResult;
+use tg_signal::{SignalNo, SignalResult};
 use tg_syscall::Caller;
 use tg_task_manage::ProcId;
 use xmas_elf::ElfFile;
@@ -92,22 +100,33 @@ impl KernelSpace {
 // 内核地址空间。
 static KERNEL_SPACE: KernelSpace = KernelSpace::new();
 
-extern "C" fn rust_main() -> ! {
+extern "C" fn rust_main(_hartid: usize, dtb: usize) -> ! {
     let layout = tg_linker::KernelLayout::locate();
     // bss 段清零
     unsafe { layout.zero_bss() };
     // 初始化 `console`
     tg_console::init_console(&Console);
     tg_console::set_log_level(option_env!("LOG"));
+    timer::init_time_slice(option_env!("TIME_SLICE_MS"));
+    // 从设备树读取时钟频率，用 RTC 校准实时时钟（此时尚未开启分页，可以直接访问物理地址）
+    if let Some(info) = unsafe { dtb::parse(dtb) } {
+        log::info!("{info:?}");
+        timer::init_timebase(info.timebase_frequency);
+        if let Some(base) = info.goldfish_rtc {
+            timer::init_realtime(rtc::GoldfishRtc::new(base).read_ns());
+        }
+    }
     tg_console::test_log();
-    // 初始化内核堆
+    // 初始化内核堆，其余物理内存交给页帧分配器
+    assert!(layout.len() + frame::KERNEL_HEAP_SIZE < MEMORY);
     tg_kernel_alloc::init(layout.start() as _);
     unsafe {
         tg_kernel_alloc::transfer(core::slice::from_raw_parts_mut(
             layout.end() as _,
-            MEMORY - layout.len(),
+            frame::KERNEL_HEAP_SIZE,
         ))
     };
+    frame::init(layout.end() + frame::KERNEL_HEAP_SIZE..layout.start() + MEMORY);
     // 建立异界传送门
     let portal_size = MultislotPortal::calculate_size(1);
     let portal_layout = Layout::from_size_align(portal_size, 1 << Sv39::PAGE_BITS).unwrap();
@@ -124,8 +143,14 @@ extern "C" fn rust_main() -> ! {
     tg_syscall::init_clock(&SyscallContext);
     tg_syscall::init_signal(&SyscallContext);
     tg_syscall::init_memory(&SyscallContext);
+    tg_syscall::init_thread(&SyscallContext);
+    tg_syscall::init_sync_mutex(&SyscallContext);
+    // 开启时钟中断，用户线程按时间片轮转
+    timer::init();
     let initproc = read_all(FS.open("initproc", OpenFlags::RDONLY).unwrap());
-    if let Some((process, thread)) = Process::from_elf(ElfFile::new(initproc.as_slice()).unwrap()) {
+    if let Ok((process, thread)) =
+        Process::from_elf(ElfFile::new(initproc.as_slice()).unwrap(), "initproc", &["initproc".into()], &[])
+    {
         PROCESSOR.get_mut().set_proc_manager(ProcManager::new());
         PROCESSOR.get_mut().set_manager(ThreadManager::new());
         let (pid, tid) = (process.pid, thread.tid);
@@ -136,16 +161,38 @@ extern "C" fn rust_main() -> ! {
     }
     loop {
         let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+        timer::check_timeout();
+        // 没有留下僵尸的进程不会被 wait 回收，丢弃为它保留的 CPU 时间
+        for pid in unsafe { (*processor).take_reaped() } {
+            unsafe { (*processor).proc_manager().take_exited_times(pid) };
+        }
         if let Some(task) = unsafe { (*processor).find_next() } {
+            timer::set_next_tick();
+            let user_start = timer::now_ns();
             unsafe { task.context.execute(portal, ()) };
+            // 从进入用户态到 trap 回来的时间计入用户态时间
+            let user_ns = timer::now_ns() - user_start;
+            task.times.user += user_ns;
+            unsafe { (*processor).get_current_proc().unwrap().times.user += user_ns };
+            // 空闲页帧不足时先换出冷页，系统调用和缺页处理中的分配因此很少失败
+            swap::reclaim(unsafe { &mut *processor });
             match scause::read().cause() {
+                scause::Trap::Interrupt(scause::Interrupt::SupervisorTimer) => {
+                    // 时间片用完，回到就绪队列
+                    unsafe { (*processor).make_current_preempted() };
+                }
                 scause::Trap::Exception(scause::Exception::UserEnvCall) => {
                     use tg_syscall::{SyscallId as Id, SyscallResult as Ret};
                     let ctx = &mut task.context.context;
                     ctx.move_next();
                     let id: Id = ctx.a(7).into();
                     let args = [ctx.a(0), ctx.a(1), ctx.a(2), ctx.a(3), ctx.a(4), ctx.a(5)];
+                    let syscall_start = timer::now_ns();
                     let syscall_ret = tg_syscall::handle(Caller { entity: 0, flow: 0 }, id, args);
+                    // 系统调用的处理时间计入内核态时间，系统调用可能新建线程，重新查找当前线程
+                    let system_ns = timer::now_ns() - syscall_start;
+                    unsafe { (*processor).current().unwrap().times.system += system_ns };
+                    unsafe { (*processor).get_current_proc().unwrap().times.system += system_ns };
                     // 目前信号处理位置放在 syscall 执行之后，这只是临时的实现。
                     // 正确处理信号的位置应该是在 “trap 中处理异常和中断和异常之后，返回用户态之前”。
                     // 例如发现有访存异常时，应该触发 SIGSEGV 信号然后进行处理。
@@ -154,26 +201,69 @@ extern "C" fn rust_main() -> ! {
                     // 最简单粗暴的方法是，在 `scause::Trap` 分类的每一条分支之后都加上信号处理，
                     // 当然这样可能代码上不够优雅。处理信号的具体时机还需要后续再讨论。
                     let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
-                    match current_proc.signal.handle_signals(ctx) {
+                    let signal_result = current_proc.signal.handle_signals(ctx);
+                    match signal_result {
                         // 进程应该结束执行
                         SignalResult::ProcessKilled(exit_code) => unsafe {
-                            (*processor).make_current_exited(exit_code as _)
+                            (*processor).make_current_group_exited(wait_status::signaled(-exit_code as isize))
                         },
                         _ => match syscall_ret {
                             Ret::Done(ret) => match id {
-                                Id::EXIT | Id::EXIT_GROUP => unsafe { (*processor).make_current_exited(ret) },
+                                // exit 只结束当前线程，最后一个线程退出时进程随之结束；exit_group 结束整个进程
+                                Id::EXIT => unsafe {
+                                    let pid = (*processor).get_current_proc().unwrap().pid;
+                                    if (*processor).thread_count(pid) == 1 {
+                                        (*processor).make_current_group_exited(wait_status::exited(ret))
+                                    } else {
+                                        (*processor).make_current_exited(ret)
+                                    }
+                                },
+                                Id::EXIT_GROUP => unsafe {
+                                    (*processor).make_current_group_exited(wait_status::exited(ret))
+                                },
+                                // 线程已进入等待队列，被唤醒后默认返回 0，超时等情况由唤醒者改写
+                                Id::FUTEX
+                                | Id::NANOSLEEP
+                                | Id::CLOCK_NANOSLEEP
+                                | Id::SEMAPHORE_DOWN
+                                | Id::MUTEX_LOCK
+                                | Id::CONDVAR_WAIT
+                                | Id::CONDVAR_TIMEDWAIT
+                                    if ret == impls::BLOCKED =>
+                                {
+                                    *task.context.context.a_mut(0) = 0;
+                                    block_current(unsafe { &mut *processor });
+                                }
+                                // wait4 没有可报告的子进程：阻塞到子进程状态变化后重新执行 ecall；
+                                // 期间处理了信号则返回 -EINTR
+                                Id::WAIT4 if ret == impls::BLOCKED => {
+                                    if matches!(signal_result, SignalResult::Handled) {
+                                        *task.context.context.a_mut(0) = -4isize as _; // -EINTR
+                                        unsafe { (*processor).make_current_suspend() };
+                                    } else {
+                                        *task.context.context.pc_mut() -= 4;
+                                        crate::processor::wait_child_event(task.tid);
+                                        block_current(unsafe { &mut *processor });
+                                    }
+                                }
                                 _ => {
                                     let ctx = &mut task.context.context;
                                     *ctx.a_mut(0) = ret as _;
-                                    unsafe { (*processor).make_current_suspend() };
+                                    if matches!(signal_result, SignalResult::ProcessSuspended) {
+                                        stop_current(unsafe { &mut *processor });
+                                    } else {
+                                        unsafe { (*processor).make_current_suspend() };
+                                    }
                                 }
                             },
                             Ret::Unsupported(_) => {
                                 log::error!("Unsupported syscall: id = {id:?}");
                                 log::error!("  Syscall args: [{:#x}, {:#x}, {:#x}, {:#x}, {:#x}, {:#x}]", 
                                     args[0], args[1], args[2], args[3], args[4], args[5]);
-                                log::error!("  Process will exit with code -2");
-                                unsafe { (*processor).make_current_exited(-2) };
+                                log::error!("  Process will be killed by SIGSYS");
+                                unsafe {
+                                    (*processor).make_current_group_exited(wait_status::signaled(SignalNo::SIGSYS as _))
+                                };
                             }
                         },
                     }
@@ -185,7 +275,20 @@ extern "C" fn rust_main() -> ! {
                     let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
                     let sepc_val = sepc::read();
                     log::info!("Program reached breakpoint at {:#x}, exiting with success", sepc_val);
-                    unsafe { (*processor).make_current_exited(0) };
+                    unsafe { (*processor).make_current_group_exited(0) };
+                }
+                scause::Trap::Exception(
+                    e @ (scause::Exception::LoadPageFault
+                    | scause::Exception::StorePageFault
+                    | scause::Exception::InstructionPageFault),
+                ) if handle_page_fault(
+                    unsafe { &mut *processor },
+                    e == scause::Exception::StorePageFault,
+                    stval::read(),
+                ) =>
+                {
+                    // 缺页已经处理，回到用户态重新执行访存指令
+                    unsafe { (*processor).make_current_suspend() };
                 }
                 e => {
                     let ctx = &task.context.context;
@@ -208,12 +311,19 @@ extern "C" fn rust_main() -> ! {
                     log::error!("  Register a7 (syscall id): {:#x}", ctx.a(7));
                     log::error!("  Register sp: {:#x}", ctx.sp());
                     log::error!("  Register ra: {:#x}", ctx.ra());
-                    log::error!("  Process will exit with code -3");
+                    log::error!("  Process will be killed by SIGSEGV");
                     log::error!("════════════════════════════════════════════════════════════");
                     
-                    unsafe { (*processor).make_current_exited(-3) };
+                    unsafe {
+                        (*processor).make_current_group_exited(wait_status::signaled(SignalNo::SIGSEGV as _))
+                    };
                 }
             }
+        } else if let Some(deadline) = timer::next_deadline() {
+            // 所有线程都在等待，空转到最早的超时时刻
+            while timer::now_ns() < deadline {
+                core::hint::spin_loop();
+            }
         } else {
             println!("no task");
             break;
@@ -223,6 +333,35 @@ extern "C" fn rust_main() -> ! {
     tg_sbi::shutdown(false)
 }
 
+/// 当前线程因 SIGSTOP 停止，挂起直到进程收到 SIGCONT。
+///
+/// 进程中第一个停止的线程向父进程报告停止状态。
+fn stop_current(processor: &mut ProcessorInner) {
+    let tid = processor.current_tid().unwrap();
+    let proc = processor.get_current_proc().unwrap();
+    let pid = proc.pid;
+    let first = proc.stopped_threads.is_empty();
+    proc.stopped_threads.push(tid);
+    if first {
+        processor.child_stopped(pid, wait_status::stopped(SignalNo::SIGSTOP as _));
+        crate::processor::notify_child_event();
+    }
+    block_current(processor);
+}
+
+/// 处理用户态缺页：写入写时复制的页时复制该页，访问换出的页时从交换区读回，访问按需分配的区域时分配清零的页。
+///
+/// 地址不在任何区域中、访问权限不符或换出冷页后仍然没有物理页时返回 `false`，由调用者按段错误处理。
+fn handle_page_fault(processor: &mut ProcessorInner, store: bool, addr: usize) -> bool {
+    let space = &mut processor.get_current_proc().unwrap().address_space;
+    let vaddr = VAddr::new(addr);
+    if (store && space.copy_on_write(vaddr)) || space.populate(vaddr) {
+        return true;
+    }
+    log::error!("page fault at {:#x}: unmapped address or out of memory, {}", addr, frame::stats());
+    false
+}
+
 /// Rust 异常处理函数，以异常方式关机。
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
@@ -230,11 +369,12 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
     tg_sbi::shutdown(true)
 }
 
-/// Virtio Block in virt machine
-pub const MMIO: &[(usize, usize)] = &[(0x1000_1000, 0x00_1000)];
+/// Virtio Block in virt machine，第二块为交换设备
+pub const MMIO: &[(usize, usize)] = &[(0x1000_1000, 0x00_1000), (0x1000_2000, 0x00_1000)];
 
 fn kernel_space(layout: tg_linker::KernelLayout, memory: usize, portal: usize) {
-    let mut space = AddressSpace::new();
+    // 启动时页帧充足，建立内核映射不会失败
+    let mut space = AddressSpace::new().unwrap();
     for region in layout.iter() {
         log::info!("{region}");
         use tg_linker::KernelRegionTitle::*;
@@ -245,25 +385,25 @@ fn kernel_space(layout: tg_linker::KernelLayout, memory: usize, portal: usize) {
         };
         let s = VAddr::<Sv39>::new(region.range.start);
         let e = VAddr::<Sv39>::new(region.range.end);
-        space.map_extern(
+        assert!(space.map_extern(
             s.floor()..e.ceil(),
             PPN::new(s.floor().val()),
             build_flags(flags),
-        )
+        ));
     }
     let s = VAddr::<Sv39>::new(layout.end());
     let e = VAddr::<Sv39>::new(layout.start() + memory);
     log::info!("(heap) ---> {:#10x}..{:#10x}", s.val(), e.val());
-    space.map_extern(
+    assert!(space.map_extern(
         s.floor()..e.ceil(),
         PPN::new(s.floor().val()),
         build_flags("_WRV"),
-    );
-    space.map_extern(
+    ));
+    assert!(space.map_extern(
         PROTAL_TRANSIT..PROTAL_TRANSIT + 1,
         PPN::new(portal >> Sv39::PAGE_BITS),
         build_flags("__G_XWRV"),
-    );
+    ));
     println!();
 
     // MMIO
@@ -271,11 +411,11 @@ fn kernel_space(layout: tg_linker::KernelLayout, memory: usize, portal: usize) {
         let s = VAddr::<Sv39>::new(*base);
         let e = VAddr::<Sv39>::new(*base + *len);
         log::info!("MMIO range -> {:#10x}..{:#10x}", s.val(), e.val());
-        space.map_extern(
+        assert!(space.map_extern(
             s.floor()..e.ceil(),
             PPN::new(s.floor().val()),
             build_flags("_WRV"),
-        );
+        ));
     }
 
     unsafe { satp::set(satp::Mode::Sv39, 0, space.root_ppn().val()) };
@@ -283,31 +423,39 @@ fn kernel_space(layout: tg_linker::KernelLayout, memory: usize, portal: usize) {
 }
 
 /// 映射异界传送门。
+///
+/// 只复制下级页表的页号，不带 `OWNED` 标志，地址空间释放时不会回收内核的页表。
 fn map_portal(space: &AddressSpace<Sv39, Sv39Manager>) {
     let portal_idx = PROTAL_TRANSIT.index_in(Sv39::MAX_LEVEL);
-    space.root()[portal_idx] = unsafe { KERNEL_SPACE.assume_init_ref() }.root()[portal_idx];
+    let pte = unsafe { KERNEL_SPACE.assume_init_ref() }.root()[portal_idx];
+    space.root()[portal_idx] = VmFlags::VALID.build_pte(pte.ppn());
 }
 
 /// 各种接口库的实现。
 mod impls {
     use crate::{
         build_flags,
-        fs::{read_all, Fd, FS},
-        processor::ProcessorInner,
-        Sv39, PROCESSOR,
+        frame::{self, FrameKind},
+        fs::{read_all, Fd, FileSource, FS},
+        futex::{self, FutexKey},
+        parse_flags,
+        process::{open_interp, wait_status, Process as ProcessStruct, USER_STACK_PAGES, USER_TOP_VPN},
+        processor::{cancel_child_wait, notify_child_event, set_blocked_ret, wake_thread, ProcessorInner},
+        sleep, swap, timer, Sv39, PROCESSOR,
     };
-    use alloc::{alloc::alloc_zeroed, string::String, vec::Vec};
-    use core::{alloc::Layout, ptr::NonNull};
+    use alloc::{string::String, sync::Arc, vec::Vec};
+    use core::ptr::NonNull;
     use spin::Mutex;
     use tg_console::log;
     use tg_easy_fs::{make_pipe, FSManager, OpenFlags, UserBuffer};
     use tg_kernel_vm::{
         page_table::{MmuMeta, Pte, VAddr, VmFlags, PPN, VPN},
-        PageManager,
+        AddressSpace, PageManager, PageSource,
     };
     use tg_signal::SignalNo;
+    use tg_sync::{Condvar, Mutex as MutexTrait, MutexBlocking, Semaphore};
     use tg_syscall::*;
-    use tg_task_manage::ProcId;
+    use tg_task_manage::{ProcId, SchedPolicy, ThreadId, WaitResult, WaitTarget};
     use xmas_elf::ElfFile;
 
     #[repr(transparent)]
@@ -316,22 +464,26 @@ mod impls {
     impl Sv39Manager {
         const OWNED: VmFlags<Sv39> = unsafe { VmFlags::from_raw(1 << 8) };
 
+        /// 分配页帧，没有空闲页帧时从其他进程换出冷页后重试一次，仍然失败时返回 `None`。
         #[inline]
-        fn page_alloc<T>(count: usize) -> *mut T {
-            unsafe {
-                alloc_zeroed(Layout::from_size_align_unchecked(
-                    count << Sv39::PAGE_BITS,
-                    1 << Sv39::PAGE_BITS,
-                ))
-            }
-            .cast()
+        fn page_alloc<T>(count: usize, kind: FrameKind) -> Option<NonNull<T>> {
+            let ppn = frame::alloc(count, kind).or_else(|| {
+                swap::reclaim_for_alloc(PROCESSOR.get_mut());
+                frame::alloc(count, kind)
+            })?;
+            NonNull::new((ppn << Sv39::PAGE_BITS) as _)
         }
     }
 
     impl PageManager<Sv39> for Sv39Manager {
+        const COW: VmFlags<Sv39> = unsafe { VmFlags::from_raw(1 << 9) };
+        const WRITABLE: VmFlags<Sv39> = build_flags("W__");
+        const ACCESSED: VmFlags<Sv39> = unsafe { VmFlags::from_raw(1 << 6) };
+        const DIRTY: VmFlags<Sv39> = unsafe { VmFlags::from_raw(1 << 7) };
+
         #[inline]
-        fn new_root() -> Self {
-            Self(NonNull::new(Self::page_alloc(1)).unwrap())
+        fn new_root() -> Option<Self> {
+            Self::page_alloc(1, FrameKind::PageTable).map(Self)
         }
 
         #[inline]
@@ -360,17 +512,75 @@ mod impls {
         }
 
         #[inline]
-        fn allocate(&mut self, len: usize, flags: &mut VmFlags<Sv39>) -> NonNull<u8> {
+        fn allocate(&mut self, len: usize, flags: &mut VmFlags<Sv39>) -> Option<NonNull<u8>> {
+            // 非叶子的页表项指向页表页
+            let kind = if Sv39::is_leaf(flags.val()) {
+                FrameKind::User
+            } else {
+                FrameKind::PageTable
+            };
             *flags |= Self::OWNED;
-            NonNull::new(Self::page_alloc(len)).unwrap()
+            Self::page_alloc(len, kind)
+        }
+
+        #[inline]
+        fn deallocate(&mut self, pte: Pte<Sv39>, len: usize) -> usize {
+            frame::dealloc(pte.ppn().val(), len)
+        }
+
+        #[inline]
+        fn share(&self, pte: Pte<Sv39>) {
+            frame::share(pte.ppn().val());
+        }
+
+        #[inline]
+        fn is_shared(&self, pte: Pte<Sv39>) -> bool {
+            frame::ref_count(pte.ppn().val()) > 1
+        }
+
+        fn swap_out(&mut self, pte: Pte<Sv39>, dirty: bool) -> Option<Pte<Sv39>> {
+            let ppn = pte.ppn().val();
+            let page = unsafe {
+                core::slice::from_raw_parts(self.p_to_v::<u8>(pte.ppn()).as_ptr(), 1 << Sv39::PAGE_BITS)
+            };
+            // 换入后没有写过的页，交换区中的内容仍然有效
+            let slot = match frame::set_swap_slot(ppn, None) {
+                Some(slot) if !dirty => slot,
+                Some(slot) => {
+                    swap::write_page(slot, page);
+                    slot
+                }
+                None => {
+                    let slot = swap::alloc_slot()?;
+                    swap::write_page(slot, page);
+                    slot
+                }
+            };
+            frame::dealloc(ppn, 1);
+            let valid = build_flags("V").val() | Self::ACCESSED.val() | Self::DIRTY.val();
+            let flags = unsafe { VmFlags::from_raw(pte.flags().val() & !valid) };
+            Some(flags.build_pte(PPN::new(slot)))
         }
 
-        fn deallocate(&mut self, _pte: Pte<Sv39>, _len: usize) -> usize {
-            todo!()
+        fn swap_in(&mut self, pte: Pte<Sv39>) -> Option<Pte<Sv39>> {
+            let slot = pte.ppn().val();
+            let page = Self::page_alloc::<u8>(1, FrameKind::User)?;
+            let ppn = self.v_to_p(page).val();
+            let page = unsafe { core::slice::from_raw_parts_mut(page.as_ptr(), 1 << Sv39::PAGE_BITS) };
+            swap::read_page(slot, page);
+            frame::set_swap_slot(ppn, Some(slot));
+            let flags = unsafe { VmFlags::from_raw(pte.flags().val() | build_flags("V").val()) };
+            Some(flags.build_pte(PPN::new(ppn)))
         }
 
+        #[inline]
+        fn swap_free(&mut self, pte: Pte<Sv39>) {
+            swap::free_slot(pte.ppn().val());
+        }
+
+        #[inline]
         fn drop_root(&mut self) {
-            todo!()
+            frame::dealloc(self.root_ppn().val(), 1);
         }
     }
 
@@ -384,9 +594,37 @@ mod impls {
     }
 
     pub struct SyscallContext;
+
+    /// 系统调用让当前线程进入了等待队列，由 trap 循环阻塞它。
+    ///
+    /// 不是任何合法的返回值或错误码，不会与 `-EPERM` 等混淆。
+    pub const BLOCKED: isize = isize::MIN;
+
     const READABLE: VmFlags<Sv39> = build_flags("RV");
     const WRITEABLE: VmFlags<Sv39> = build_flags("W_V");
 
+    /// 把用户缓冲区按页翻译成内核可访问的切片，要求写权限时先解除写时复制。
+    ///
+    /// 写时复制之后相邻的虚页不一定映射到相邻的物理页，不能按一整块访问。
+    fn user_buffer(
+        proc: &mut ProcessStruct,
+        addr: usize,
+        len: usize,
+        flags: VmFlags<Sv39>,
+    ) -> Option<Vec<&'static mut [u8]>> {
+        const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+        let end = addr.checked_add(len)?;
+        let mut slices = Vec::new();
+        let mut start = addr;
+        while start < end {
+            let len = (PAGE_SIZE - (start & (PAGE_SIZE - 1))).min(end - start);
+            let ptr = proc.address_space.translate_mut::<u8>(VAddr::new(start), flags)?;
+            slices.push(unsafe { core::slice::from_raw_parts_mut(ptr.as_ptr(), len) });
+            start += len;
+        }
+        Some(slices)
+    }
+
     fn linux_open_flags(flags: u32) -> Option<OpenFlags> {
         const O_ACCMODE: u32 = 0b11;
         const O_WRONLY: u32 = 1;
@@ -414,20 +652,15 @@ mod impls {
         fn write(&self, _caller: Caller, fd: usize, buf: usize, count: usize) -> isize {
             log::debug!("sys_write <= fd: {}, buf: {:#x}, count: {}", fd, buf, count);
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
-            if let Some(ptr) = current.address_space.translate(VAddr::new(buf), READABLE) {
+            if let Some(v) = user_buffer(current, buf, count, READABLE) {
                 if fd == STDOUT || fd == STDDEBUG {
-                    print!("{}", unsafe {
-                        core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-                            ptr.as_ptr(),
-                            count,
-                        ))
-                    });
+                    for slice in v {
+                        print!("{}", unsafe { core::str::from_utf8_unchecked(slice) });
+                    }
                     count as _
                 } else if let Some(file) = &current.fd_table[fd] {
                     let file = file.lock();
                     if file.writable() {
-                        let mut v: Vec<&'static mut [u8]> = Vec::new();
-                        unsafe { v.push(core::slice::from_raw_parts_mut(ptr.as_ptr(), count)) };
                         file.write(UserBuffer::new(v)) as _
                     } else {
                         log::error!("file not writable");
@@ -446,21 +679,15 @@ mod impls {
         fn read(&self, _caller: Caller, fd: usize, buf: usize, count: usize) -> isize {
             log::debug!("sys_read <= fd: {}, buf: {:#x}, count: {}", fd, buf, count);
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
-            if let Some(ptr) = current.address_space.translate(VAddr::new(buf), WRITEABLE) {
+            if let Some(v) = user_buffer(current, buf, count, WRITEABLE) {
                 if fd == STDIN {
-                    let mut ptr = ptr.as_ptr();
-                    for _ in 0..count {
-                        unsafe {
-                            *ptr = tg_sbi::console_getchar() as u8;
-                            ptr = ptr.add(1);
-                        }
+                    for byte in v.into_iter().flatten() {
+                        *byte = tg_sbi::console_getchar() as u8;
                     }
                     count as _
                 } else if let Some(file) = &current.fd_table[fd] {
                     let file = file.lock();
                     if file.readable() {
-                        let mut v: Vec<&'static mut [u8]> = Vec::new();
-                        unsafe { v.push(core::slice::from_raw_parts_mut(ptr.as_ptr(), count)) };
                         file.read(UserBuffer::new(v)) as _
                     } else {
                         log::error!("file not readable");
@@ -479,7 +706,7 @@ mod impls {
         fn open(&self, _caller: Caller, dirfd: isize, path: usize, flags: usize, _mode: usize) -> isize {
             log::debug!("sys_openat <= dirfd: {}, path: {:#x}, flags: {:#x}, mode: {:#x}", dirfd, path, flags, _mode);
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
-            if let Some(ptr) = current.address_space.translate(VAddr::new(path), READABLE) {
+            if let Some(ptr) = current.address_space.translate_mut(VAddr::new(path), READABLE) {
                 let mut string = String::new();
                 let mut raw_ptr: *mut u8 = ptr.as_ptr();
                 loop {
@@ -493,7 +720,8 @@ mod impls {
                     }
                 }
 
-                let flags = match linux_open_flags(flags as u32) {
+                let flags_raw = flags as u32;
+                let flags = match linux_open_flags(flags_raw) {
                     Some(flags) => flags,
                     None => return -1,
                 };
@@ -503,6 +731,9 @@ mod impls {
                     current
                         .fd_table
                         .push(Some(Mutex::new(Fd::File((*file_handle).clone()))));
+                    if flags_raw & linux_raw_sys::general::O_CLOEXEC != 0 {
+                        current.fd_cloexec.insert(new_fd);
+                    }
                     new_fd as isize
                 } else {
                     -1
@@ -521,7 +752,7 @@ mod impls {
             }
             if let Some(mut ptr) = current
                 .address_space
-                .translate::<Stat>(VAddr::new(st), WRITEABLE)
+                .translate_mut::<Stat>(VAddr::new(st), WRITEABLE)
             {
                 let stat = unsafe { ptr.as_mut() };
                 let file = current.fd_table[fd].as_ref().unwrap().lock();
@@ -563,6 +794,7 @@ mod impls {
                 return -1;
             }
             current.fd_table[fd].take();
+            current.fd_cloexec.remove(&fd);
             0
         }
 
@@ -575,7 +807,7 @@ mod impls {
             // 将 read_fd 写入 pipe[0]
             if let Some(mut ptr) = current
                 .address_space
-                .translate::<usize>(VAddr::new(pipe), WRITEABLE)
+                .translate_mut::<usize>(VAddr::new(pipe), WRITEABLE)
             {
                 unsafe { *ptr.as_mut() = read_fd };
             } else {
@@ -584,7 +816,7 @@ mod impls {
             // 将 write_fd 写入 pipe[1]
             if let Some(mut ptr) = current
                 .address_space
-                .translate::<usize>(VAddr::new(pipe + core::mem::size_of::<usize>()), WRITEABLE)
+                .translate_mut::<usize>(VAddr::new(pipe + core::mem::size_of::<usize>()), WRITEABLE)
             {
                 unsafe { *ptr.as_mut() = write_fd };
             } else {
@@ -633,8 +865,8 @@ mod impls {
             new_fd as isize
         }
         
-        fn fcntl(&self, _caller: Caller, fd: usize, cmd: i32, _arg: usize) -> isize {
-            log::debug!("sys_fcntl <= fd: {}, cmd: {}, arg: {}", fd, cmd, _arg);
+        fn fcntl(&self, _caller: Caller, fd: usize, cmd: i32, arg: usize) -> isize {
+            log::debug!("sys_fcntl <= fd: {}, cmd: {}, arg: {}", fd, cmd, arg);
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
             
             // 检查文件描述符是否有效
@@ -648,26 +880,40 @@ mod impls {
             const F_SETFD: i32 = 2;      // 设置关闭时执行标志
             const F_GETFL: i32 = 3;      // 获取文件打开标志
             const F_SETFL: i32 = 4;      // 设置文件打开标志
+            const F_DUPFD_CLOEXEC: i32 = 1030; // 复制文件描述符并设置关闭时执行标志
+            const FD_CLOEXEC: usize = 1; // 关闭时执行标志
             const O_RDWR: i32 = 2;       // 读写打开标志
             
             match cmd {
-                F_DUPFD => {
-                    // F_DUPFD: 复制 fd，使用 >= _arg 的最小可用文件描述符
-                    // 简化实现：忽略 _arg 参数，直接复制到最后
+                F_DUPFD | F_DUPFD_CLOEXEC => {
+                    // F_DUPFD: 复制 fd，使用 >= arg 的最小可用文件描述符
+                    // 简化实现：忽略 arg 参数，直接复制到最后
                     let new_file = {
                         let old_file = current.fd_table[fd].as_ref().unwrap();
                         old_file.lock().clone()
                     };
                     let new_fd = current.fd_table.len();
                     current.fd_table.push(Some(Mutex::new(new_file)));
+                    if cmd == F_DUPFD_CLOEXEC {
+                        current.fd_cloexec.insert(new_fd);
+                    }
                     new_fd as isize
                 }
                 F_GETFD => {
-                    // 获取 close-on-exec 标志，简化实现返回 0
-                    0
+                    // 获取 close-on-exec 标志
+                    if current.fd_cloexec.contains(&fd) {
+                        FD_CLOEXEC as isize
+                    } else {
+                        0
+                    }
                 }
                 F_SETFD => {
-                    // 设置 close-on-exec 标志，简化实现返回 0
+                    // 设置 close-on-exec 标志
+                    if arg & FD_CLOEXEC != 0 {
+                        current.fd_cloexec.insert(fd);
+                    } else {
+                        current.fd_cloexec.remove(&fd);
+                    }
                     0
                 }
                 F_GETFL => {
@@ -691,6 +937,24 @@ mod impls {
         #[inline]
         fn exit(&self, _caller: Caller, exit_code: usize) -> isize {
             log::debug!("sys_exit <= exit_code: {}", exit_code);
+            // 线程退出时按 `CLONE_CHILD_CLEARTID` 的约定将 tid 清零，供 pthread_join 观察
+            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+            let current = unsafe { (*processor).get_current_proc().unwrap() };
+            let thread = unsafe { (*processor).current().unwrap() };
+            if thread.clear_child_tid != 0 {
+                if let Some(mut ptr) = current
+                    .address_space
+                    .translate_mut::<i32>(VAddr::new(thread.clear_child_tid), WRITEABLE)
+                {
+                    unsafe { *ptr.as_mut() = 0 };
+                    if let Some(key) = futex_key(current, thread.clear_child_tid, false) {
+                        futex::wake(key, 1, linux_raw_sys::general::FUTEX_BITSET_MATCH_ANY);
+                    }
+                }
+            }
+            // 已退出的线程不再参与死锁检测
+            current.mutex_detector.remove_thread(thread.tid);
+            current.semaphore_detector.remove_thread(thread.tid);
             exit_code as isize
         }
 
@@ -700,68 +964,214 @@ mod impls {
             exit_code as isize
         }
 
-        fn fork(&self, _caller: Caller) -> isize {
-            log::debug!("sys_fork <=");
+        fn fork(&self, caller: Caller) -> isize {
+            Process::clone(self, caller, SignalNo::SIGCHLD as _, 0, 0, 0, 0)
+        }
+
+        fn clone(
+            &self,
+            _caller: Caller,
+            flags: usize,
+            stack: usize,
+            ptid: usize,
+            tls: usize,
+            ctid: usize,
+        ) -> isize {
+            log::debug!(
+                "sys_clone <= flags: {:#x}, stack: {:#x}, ptid: {:#x}, tls: {:#x}, ctid: {:#x}",
+                flags, stack, ptid, tls, ctid
+            );
+            use linux_raw_sys::general::{
+                CLONE_CHILD_CLEARTID, CLONE_CHILD_SETTID, CLONE_PARENT_SETTID, CLONE_SETTLS,
+                CLONE_SIGHAND, CLONE_THREAD, CLONE_VM,
+            };
+            let flags = flags as u32;
+            // 与 Linux 相同：CLONE_THREAD 要求 CLONE_SIGHAND，CLONE_SIGHAND 要求 CLONE_VM
+            if (flags & CLONE_THREAD != 0 && flags & CLONE_SIGHAND == 0)
+                || (flags & CLONE_SIGHAND != 0 && flags & CLONE_VM == 0)
+            {
+                return -22; // -EINVAL
+            }
             let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
             let current_proc = unsafe { (*processor).get_current_proc().unwrap() };
-            let parent_pid = current_proc.pid; // 先保存父进程 pid
-            let (proc, mut thread) = current_proc.fork().unwrap();
-            let pid = proc.pid;
-            *thread.context.context.a_mut(0) = 0 as _;
-            unsafe {
-                (*processor).add_proc(pid, proc, parent_pid);
-                (*processor).add(thread.tid, thread, pid);
+            // 子控制流从当前线程 ecall 的下一条指令开始执行，并从 clone 返回 0
+            let mut context = unsafe { (*processor).current().unwrap().context.context.clone() };
+            *context.a_mut(0) = 0;
+            if stack != 0 {
+                *context.sp_mut() = stack;
             }
-            pid.get_usize() as isize
+            if flags & CLONE_SETTLS != 0 {
+                *context.x_mut(4) = tls;
+            }
+            let (mut thread, pid, ret) = if flags & CLONE_THREAD != 0 {
+                let thread = current_proc.new_thread(context);
+                let tid = thread.tid.get_usize();
+                (thread, current_proc.pid, tid)
+            } else {
+                if flags & CLONE_VM != 0 {
+                    // 不支持进程间共享地址空间（vfork/posix_spawn），退化为复制地址空间
+                    log::warn!("sys_clone: CLONE_VM without CLONE_THREAD, fall back to fork");
+                }
+                let parent_pid = current_proc.pid; // 先保存父进程 pid
+                let Some((proc, thread)) = current_proc.fork(context) else {
+                    return -12; // -ENOMEM
+                };
+                let pid = proc.pid;
+                unsafe { (*processor).add_proc(pid, proc, parent_pid) };
+                (thread, pid, pid.get_usize())
+            };
+            let tid = thread.tid.get_usize() as i32;
+            if flags & CLONE_PARENT_SETTID != 0 {
+                if let Some(mut ptr) = current_proc
+                    .address_space
+                    .translate_mut::<i32>(VAddr::new(ptid), WRITEABLE)
+                {
+                    unsafe { *ptr.as_mut() = tid };
+                }
+            }
+            if flags & CLONE_CHILD_SETTID != 0 {
+                // 写入子控制流所在的地址空间
+                let child_proc = unsafe { (*processor).get_proc(pid).unwrap() };
+                if let Some(mut ptr) = child_proc
+                    .address_space
+                    .translate_mut::<i32>(VAddr::new(ctid), WRITEABLE)
+                {
+                    unsafe { *ptr.as_mut() = tid };
+                }
+            }
+            if flags & CLONE_CHILD_CLEARTID != 0 {
+                thread.clear_child_tid = ctid;
+            }
+            inherit_sched_attr(thread.tid);
+            unsafe { (*processor).add(thread.tid, thread, pid) };
+            ret as isize
         }
 
-        fn exec(&self, _caller: Caller, path: usize, count: usize) -> isize {
-            log::debug!("sys_exec <= path: {:#x}, count: {}", path, count);
-            const READABLE: VmFlags<Sv39> = build_flags("RV");
+        fn execve(&self, _caller: Caller, path: usize, argv: usize, envp: usize) -> isize {
+            log::debug!("sys_execve <= path: {:#x}, argv: {:#x}, envp: {:#x}", path, argv, envp);
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
-            current
-                .address_space
-                .translate(VAddr::new(path), READABLE)
-                .map(|ptr| unsafe {
-                    core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr.as_ptr(), count))
-                })
-                .and_then(|name| FS.open(name, OpenFlags::RDONLY))
-                .map_or_else(
-                    || {
-                        log::error!("unknown app, select one in the list: ");
-                        FS.readdir("")
-                            .unwrap()
-                            .into_iter()
-                            .for_each(|app| println!("{app}"));
-                        println!();
-                        -1
-                    },
-                    |fd| {
-                        current.exec(ElfFile::new(&read_all(fd)).unwrap());
-                        0
-                    },
-                )
+            let (name, mut args, envs) = match read_exec_args(current, path, argv, envp) {
+                Ok(exec_args) => exec_args,
+                Err(errno) => return errno,
+            };
+            let Some(fd) = FS.open(&name, OpenFlags::RDONLY) else {
+                log::error!("unknown app, select one in the list: ");
+                FS.readdir("")
+                    .unwrap()
+                    .into_iter()
+                    .for_each(|app| println!("{app}"));
+                println!();
+                return -2; // -ENOENT
+            };
+            let mut data = read_all(fd);
+            // 脚本交给 `#!` 行指定的解释器执行，解释器本身也可以是脚本
+            const MAX_SCRIPT_DEPTH: usize = 4;
+            let mut script = name.clone();
+            let mut depth = 0;
+            while let Some(shebang) = parse_shebang(&data) {
+                let (interp, arg) = match shebang {
+                    Ok(shebang) => shebang,
+                    Err(errno) => return errno,
+                };
+                depth += 1;
+                if depth > MAX_SCRIPT_DEPTH {
+                    return -40; // -ELOOP
+                }
+                let Some(fd) = open_interp(&interp) else {
+                    return -2; // -ENOENT
+                };
+                data = read_all(fd);
+                // 解释器、可选参数和脚本路径替换原来的 argv[0]
+                let mut interp_args = vec![interp.clone()];
+                interp_args.extend(arg);
+                interp_args.push(core::mem::replace(&mut script, interp));
+                interp_args.extend(args.into_iter().skip(1));
+                args = interp_args;
+            }
+            let Ok(elf) = ElfFile::new(&data) else {
+                return -8; // -ENOEXEC
+            };
+            match current.exec(elf, &name, &args, &envs) {
+                Ok(()) => 0,
+                Err(errno) => errno,
+            }
         }
 
-        fn wait(&self, _caller: Caller, pid: isize, exit_code_ptr: usize) -> isize {
-            log::debug!("sys_wait <= pid: {}, exit_code_ptr: {:#x}", pid, exit_code_ptr);
+        fn wait4(&self, _caller: Caller, pid: isize, wstatus: usize, options: u32, rusage: usize) -> isize {
+            use linux_raw_sys::general::{
+                __WALL, __WCLONE, __WNOTHREAD, WCONTINUED, WNOHANG, WUNTRACED,
+            };
+            log::debug!(
+                "sys_wait4 <= pid: {}, wstatus: {:#x}, options: {:#x}, rusage: {:#x}",
+                pid, wstatus, options, rusage
+            );
+            if options & !(WNOHANG | WUNTRACED | WCONTINUED | __WALL | __WCLONE | __WNOTHREAD) != 0 {
+                return -22; // -EINVAL
+            }
             let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
             let current = unsafe { (*processor).get_current_proc().unwrap() };
-            const WRITABLE: VmFlags<Sv39> = build_flags("W_V");
-            if let Some((dead_pid, exit_code)) =
-                unsafe { (*processor).wait(ProcId::from_usize(pid as usize)) }
-            {
-                if let Some(mut ptr) = current
+            let target = match pid {
+                -1 => WaitTarget::Any,
+                0 => WaitTarget::Group(unsafe { (*processor).get_pgid(current.pid) }.unwrap()),
+                pid if pid < 0 => match pid.checked_neg() {
+                    Some(pgid) => WaitTarget::Group(ProcId::from_usize(pgid as usize)),
+                    None => return -10, // -ECHILD
+                },
+                pid => WaitTarget::Pid(ProcId::from_usize(pid as usize)),
+            };
+            // 先检查用户指针，避免回收了子进程却无法报告
+            let mut status_ptr = None;
+            if wstatus != 0 {
+                match current.address_space.translate_mut::<i32>(VAddr::new(wstatus), WRITEABLE) {
+                    Some(ptr) => status_ptr = Some(ptr),
+                    None => return -14, // -EFAULT
+                }
+            }
+            let mut rusage_ptr = None;
+            if rusage != 0 {
+                match current
                     .address_space
-                    .translate::<i32>(VAddr::new(exit_code_ptr), WRITABLE)
+                    .translate_mut::<linux_raw_sys::general::rusage>(VAddr::new(rusage), WRITEABLE)
                 {
-                    unsafe { *ptr.as_mut() = exit_code as i32 };
+                    Some(ptr) => rusage_ptr = Some(ptr),
+                    None => return -14, // -EFAULT
                 }
-                return dead_pid.get_usize() as isize;
-            } else {
-                // 等待的子进程不存在
-                return -1;
             }
+            let result = unsafe {
+                (*processor).wait(target, options & WUNTRACED != 0, options & WCONTINUED != 0)
+            };
+            let (child, status, times) = match result {
+                WaitResult::NoChild => return -10, // -ECHILD
+                // 由 trap 循环阻塞当前线程，子进程状态变化后重新执行
+                WaitResult::Pending => return if options & WNOHANG != 0 { 0 } else { BLOCKED },
+                WaitResult::Exited(child, status) => {
+                    let times = unsafe { (*processor).proc_manager().take_exited_times(child) };
+                    current.children_times.add(times);
+                    (child, status, times)
+                }
+                WaitResult::Stopped(child, status) => {
+                    let times = unsafe { (*processor).get_proc(child) }.map_or_else(Default::default, |p| p.times);
+                    (child, status, times)
+                }
+                WaitResult::Continued(child) => {
+                    let times = unsafe { (*processor).get_proc(child) }.map_or_else(Default::default, |p| p.times);
+                    (child, wait_status::CONTINUED, times)
+                }
+            };
+            if let Some(mut ptr) = status_ptr {
+                unsafe { *ptr.as_mut() = status as i32 };
+            }
+            if let Some(mut ptr) = rusage_ptr {
+                let user = TimeVal::from_nanosecond(times.user);
+                let system = TimeVal::from_nanosecond(times.system);
+                let usage = unsafe { ptr.as_mut() };
+                *usage = unsafe { core::mem::zeroed() };
+                usage.ru_utime.tv_sec = user.tv_sec as _;
+                usage.ru_utime.tv_usec = user.tv_usec as _;
+                usage.ru_stime.tv_sec = system.tv_sec as _;
+                usage.ru_stime.tv_usec = system.tv_usec as _;
+            }
+            child.get_usize() as isize
         }
 
         fn getpid(&self, _caller: Caller) -> isize {
@@ -769,12 +1179,90 @@ mod impls {
             let current = PROCESSOR.get_mut().get_current_proc().unwrap();
             current.pid.get_usize() as _
         }
+
+        fn getppid(&self, _caller: Caller) -> isize {
+            log::debug!("sys_getppid <=");
+            let processor = PROCESSOR.get_mut();
+            let pid = processor.get_current_proc().unwrap().pid;
+            processor.get_parent(pid).map_or(0, |parent| parent.get_usize() as isize)
+        }
+
+        fn setpgid(&self, _caller: Caller, pid: isize, pgid: isize) -> isize {
+            log::debug!("sys_setpgid <= pid: {}, pgid: {}", pid, pgid);
+            if pgid < 0 {
+                return -22; // -EINVAL
+            }
+            let processor = PROCESSOR.get_mut();
+            let current = processor.get_current_proc().unwrap().pid;
+            let target = if pid == 0 { current } else { ProcId::from_usize(pid as usize) };
+            // 只能修改自己或子进程
+            if target != current && processor.get_parent(target) != Some(current) {
+                return -3; // -ESRCH
+            }
+            // 子进程须在同一会话中，会话首进程不能换组
+            let sid = processor.get_sid(current).unwrap();
+            if processor.get_sid(target) != Some(sid) || target == sid {
+                return -1; // -EPERM
+            }
+            let pgid = if pgid == 0 { target } else { ProcId::from_usize(pgid as usize) };
+            // 加入已有的进程组时，该组须在同一会话中
+            if pgid != target
+                && !processor
+                    .procs_in_group(pgid)
+                    .any(|id| processor.get_sid(id) == Some(sid))
+            {
+                return -1; // -EPERM
+            }
+            processor.set_pgid(target, pgid);
+            0
+        }
+
+        fn getpgid(&self, _caller: Caller, pid: isize) -> isize {
+            log::debug!("sys_getpgid <= pid: {}", pid);
+            let processor = PROCESSOR.get_mut();
+            let target = if pid == 0 {
+                processor.get_current_proc().unwrap().pid
+            } else {
+                ProcId::from_usize(pid as usize)
+            };
+            match processor.get_pgid(target) {
+                Some(pgid) => pgid.get_usize() as isize,
+                None => -3, // -ESRCH
+            }
+        }
+
+        fn setsid(&self, _caller: Caller) -> isize {
+            log::debug!("sys_setsid <=");
+            let processor = PROCESSOR.get_mut();
+            let pid = processor.get_current_proc().unwrap().pid;
+            // 进程组首进程不能新建会话
+            if processor.procs_in_group(pid).next().is_some() {
+                return -1; // -EPERM
+            }
+            processor.set_sid(pid);
+            pid.get_usize() as isize
+        }
+
+        fn getsid(&self, _caller: Caller, pid: isize) -> isize {
+            log::debug!("sys_getsid <= pid: {}", pid);
+            let processor = PROCESSOR.get_mut();
+            let target = if pid == 0 {
+                processor.get_current_proc().unwrap().pid
+            } else {
+                ProcId::from_usize(pid as usize)
+            };
+            match processor.get_sid(target) {
+                Some(sid) => sid.get_usize() as isize,
+                None => -3, // -ESRCH
+            }
+        }
         
-        fn set_tid_address(&self, _caller: Caller, _tidp: usize) -> isize {
-            log::debug!("sys_set_tid_address <= tidp: {:#x}", _tidp);
-            // 简化实现：只是返回 PID，不实际使用 _tidp
-            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
-            current.pid.get_usize() as isize
+        fn set_tid_address(&self, _caller: Caller, tidp: usize) -> isize {
+            log::debug!("sys_set_tid_address <= tidp: {:#x}", tidp);
+            // 记录线程退出时需要清零的地址，返回调用者的 tid
+            let thread = PROCESSOR.get_mut().current().unwrap();
+            thread.clear_child_tid = tidp;
+            thread.tid.get_usize() as isize
         }
         
         fn set_robust_list(&self, _caller: Caller, _head: usize, _len: usize) -> isize {
@@ -783,6 +1271,107 @@ mod impls {
             // 返回 0 表示成功
             0
         }
+
+        fn futex(
+            &self,
+            _caller: Caller,
+            uaddr: usize,
+            op: u32,
+            val: u32,
+            timeout: usize,
+            uaddr2: usize,
+            val3: u32,
+        ) -> isize {
+            log::debug!(
+                "sys_futex <= uaddr: {:#x}, op: {:#x}, val: {}, timeout: {:#x}, uaddr2: {:#x}, val3: {:#x}",
+                uaddr, op, val, timeout, uaddr2, val3
+            );
+            use linux_raw_sys::general::{
+                FUTEX_BITSET_MATCH_ANY, FUTEX_CLOCK_REALTIME, FUTEX_CMP_REQUEUE, FUTEX_PRIVATE_FLAG,
+                FUTEX_REQUEUE, FUTEX_WAIT, FUTEX_WAIT_BITSET, FUTEX_WAKE, FUTEX_WAKE_BITSET,
+            };
+            if uaddr & 3 != 0 {
+                return -22; // -EINVAL
+            }
+            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+            let current = unsafe { (*processor).get_current_proc().unwrap() };
+            let Some(ptr) = current.address_space.translate_mut::<u32>(VAddr::new(uaddr), READABLE) else {
+                return -14; // -EFAULT
+            };
+            let private = op & FUTEX_PRIVATE_FLAG != 0;
+            let Some(key) = futex_key(current, uaddr, private) else {
+                return -14; // -EFAULT
+            };
+            let cmd = op & !(FUTEX_PRIVATE_FLAG | FUTEX_CLOCK_REALTIME);
+            match cmd {
+                FUTEX_WAIT | FUTEX_WAIT_BITSET => {
+                    let bitset = if cmd == FUTEX_WAIT { FUTEX_BITSET_MATCH_ANY } else { val3 };
+                    if bitset == 0 {
+                        return -22; // -EINVAL
+                    }
+                    // 比较与入队之间不会被打断，不会丢失唤醒
+                    if unsafe { ptr.as_ptr().read_volatile() } != val {
+                        return -11; // -EAGAIN
+                    }
+                    let deadline = if timeout == 0 {
+                        None
+                    } else {
+                        let ns = match read_timespec(timeout) {
+                            Ok(ns) => ns,
+                            Err(errno) => return errno,
+                        };
+                        // FUTEX_WAIT 的超时是相对时间，FUTEX_WAIT_BITSET 的是绝对时间，
+                        // 按 FUTEX_CLOCK_REALTIME 决定使用实时时钟还是单调时钟
+                        Some(if cmd == FUTEX_WAIT {
+                            timer::now_ns().saturating_add(ns)
+                        } else if op & FUTEX_CLOCK_REALTIME != 0 {
+                            timer::realtime_to_monotonic(ns)
+                        } else {
+                            ns
+                        })
+                    };
+                    let tid = unsafe { (*processor).current().unwrap().tid };
+                    futex::wait(key, tid, bitset);
+                    if let Some(deadline) = deadline {
+                        timer::set_timeout(tid, deadline, move || {
+                            if futex::cancel_wait(tid) {
+                                set_blocked_ret(tid, -110); // -ETIMEDOUT
+                                wake_thread(tid);
+                            }
+                        });
+                    }
+                    // 由 trap 循环阻塞当前线程
+                    BLOCKED
+                }
+                FUTEX_WAKE => futex::wake(key, val as usize, FUTEX_BITSET_MATCH_ANY) as isize,
+                FUTEX_WAKE_BITSET => {
+                    if val3 == 0 {
+                        return -22; // -EINVAL
+                    }
+                    futex::wake(key, val as usize, val3) as isize
+                }
+                FUTEX_REQUEUE | FUTEX_CMP_REQUEUE => {
+                    if cmd == FUTEX_CMP_REQUEUE && unsafe { ptr.as_ptr().read_volatile() } != val3 {
+                        return -11; // -EAGAIN
+                    }
+                    if uaddr2 & 3 != 0 {
+                        return -22; // -EINVAL
+                    }
+                    let Some(key2) = futex_key(current, uaddr2, private) else {
+                        return -14; // -EFAULT
+                    };
+                    // REQUEUE 类操作中 timeout 参数的位置存放的是 val2
+                    let (woken, requeued) =
+                        futex::requeue(key, key2, val as usize, timeout as u32 as usize);
+                    if cmd == FUTEX_REQUEUE {
+                        woken as isize
+                    } else {
+                        (woken + requeued) as isize
+                    }
+                }
+                _ => -38, // -ENOSYS
+            }
+        }
         
         fn prlimit64(&self, _caller: Caller, _pid: isize, resource: u32, new_limit: usize, old_limit: usize) -> isize {
             log::debug!("sys_prlimit64 <= pid: {}, resource: {}, new_limit: {:#x}, old_limit: {:#x}", _pid, resource, new_limit, old_limit);
@@ -799,7 +1388,7 @@ mod impls {
                 let current = PROCESSOR.get_mut().get_current_proc().unwrap();
                 if let Some(mut ptr) = current
                     .address_space
-                    .translate::<rlimit64>(VAddr::new(old_limit), WRITABLE)
+                    .translate_mut::<rlimit64>(VAddr::new(old_limit), WRITABLE)
                 {
                     unsafe {
                         // 返回一个默认的资源限制（无限制）
@@ -816,6 +1405,31 @@ mod impls {
             let _ = new_limit; // 避免未使用变量警告
             0
         }
+
+        fn prctl(&self, _caller: Caller, option: u32, arg2: usize, _arg3: usize, _arg4: usize, _arg5: usize) -> isize {
+            log::debug!("sys_prctl <= option: {}, arg2: {:#x}", option, arg2);
+            use linux_raw_sys::prctl::{PR_GET_CHILD_SUBREAPER, PR_SET_CHILD_SUBREAPER};
+            let processor = PROCESSOR.get_mut();
+            let pid = processor.get_current_proc().unwrap().pid;
+            match option {
+                PR_SET_CHILD_SUBREAPER => {
+                    processor.set_subreaper(pid, arg2 != 0);
+                    0
+                }
+                PR_GET_CHILD_SUBREAPER => {
+                    let subreaper = processor.is_subreaper(pid);
+                    let current = processor.get_current_proc().unwrap();
+                    match current.address_space.translate_mut::<i32>(VAddr::new(arg2), WRITEABLE) {
+                        Some(mut ptr) => {
+                            unsafe { *ptr.as_mut() = subreaper as i32 };
+                            0
+                        }
+                        None => -14, // -EFAULT
+                    }
+                }
+                _ => -22, // -EINVAL
+            }
+        }
     }
 
     impl Scheduling for SyscallContext {
@@ -825,59 +1439,562 @@ mod impls {
             0
         }
         
-        fn nanosleep(&self, _caller: Caller, _req: usize, _rem: usize) -> isize {
-            log::debug!("sys_nanosleep <= req: {:#x}, rem: {:#x}", _req, _rem);
-            // 简化实现：不真正睡眠，直接返回 0（成功）
-            // 在真实环境下应该解析 timespec 结构体并让出 CPU
+        fn nanosleep(&self, _caller: Caller, req: usize, rem: usize) -> isize {
+            log::debug!("sys_nanosleep <= req: {:#x}, rem: {:#x}", req, rem);
+            match read_timespec(req) {
+                Ok(ns) => sleep_until(timer::now_ns().saturating_add(ns), rem),
+                Err(errno) => errno,
+            }
+        }
+
+        fn sched_setparam(&self, _caller: Caller, pid: isize, param: usize) -> isize {
+            log::debug!("sys_sched_setparam <= pid: {}, param: {:#x}", pid, param);
+            let tid = match sched_target(pid) {
+                Ok(tid) => tid,
+                Err(errno) => return errno,
+            };
+            let Some(priority) = read_sched_priority(param) else {
+                return -14; // -EFAULT
+            };
+            let policy = PROCESSOR.get_mut().manager().scheduler().attr(tid).policy;
+            set_sched_policy(tid, policy, priority)
+        }
+
+        fn sched_setscheduler(&self, _caller: Caller, pid: isize, policy: isize, param: usize) -> isize {
+            log::debug!(
+                "sys_sched_setscheduler <= pid: {}, policy: {}, param: {:#x}",
+                pid, policy, param
+            );
+            use linux_raw_sys::general::{
+                SCHED_BATCH, SCHED_FIFO, SCHED_IDLE, SCHED_NORMAL, SCHED_RESET_ON_FORK, SCHED_RR,
+            };
+            let tid = match sched_target(pid) {
+                Ok(tid) => tid,
+                Err(errno) => return errno,
+            };
+            if policy < 0 {
+                return -22; // -EINVAL
+            }
+            // 子线程总是继承调度策略，忽略 SCHED_RESET_ON_FORK
+            let policy = match policy as u32 & !SCHED_RESET_ON_FORK {
+                SCHED_NORMAL => SchedPolicy::Normal,
+                SCHED_FIFO => SchedPolicy::Fifo,
+                SCHED_RR => SchedPolicy::RoundRobin,
+                SCHED_BATCH => SchedPolicy::Batch,
+                SCHED_IDLE => SchedPolicy::Idle,
+                _ => return -22, // -EINVAL
+            };
+            let Some(priority) = read_sched_priority(param) else {
+                return -14; // -EFAULT
+            };
+            set_sched_policy(tid, policy, priority)
+        }
+
+        fn sched_getscheduler(&self, _caller: Caller, pid: isize) -> isize {
+            log::debug!("sys_sched_getscheduler <= pid: {}", pid);
+            use linux_raw_sys::general::{SCHED_BATCH, SCHED_FIFO, SCHED_IDLE, SCHED_NORMAL, SCHED_RR};
+            let tid = match sched_target(pid) {
+                Ok(tid) => tid,
+                Err(errno) => return errno,
+            };
+            (match PROCESSOR.get_mut().manager().scheduler().attr(tid).policy {
+                SchedPolicy::Normal => SCHED_NORMAL,
+                SchedPolicy::Fifo => SCHED_FIFO,
+                SchedPolicy::RoundRobin => SCHED_RR,
+                SchedPolicy::Batch => SCHED_BATCH,
+                SchedPolicy::Idle => SCHED_IDLE,
+            }) as isize
+        }
+
+        fn sched_getparam(&self, _caller: Caller, pid: isize, param: usize) -> isize {
+            log::debug!("sys_sched_getparam <= pid: {}, param: {:#x}", pid, param);
+            let tid = match sched_target(pid) {
+                Ok(tid) => tid,
+                Err(errno) => return errno,
+            };
+            let priority = PROCESSOR.get_mut().manager().scheduler().attr(tid).rt_priority;
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            match current.address_space.translate_mut::<i32>(VAddr::new(param), WRITEABLE) {
+                Some(mut ptr) => {
+                    unsafe { *ptr.as_mut() = priority as i32 };
+                    0
+                }
+                None => -14, // -EFAULT
+            }
+        }
+
+        fn setpriority(&self, _caller: Caller, which: isize, who: isize, prio: isize) -> isize {
+            log::debug!("sys_setpriority <= which: {}, who: {}, prio: {}", which, who, prio);
+            // 与 Linux 相同，PRIO_PROCESS 的 who 是线程 ID；暂不支持按进程组和用户设置
+            if which != linux_raw_sys::general::PRIO_PROCESS as isize {
+                return -22; // -EINVAL
+            }
+            let tid = match sched_target(who) {
+                Ok(tid) => tid,
+                Err(errno) => return errno,
+            };
+            let scheduler = PROCESSOR.get_mut().manager().scheduler();
+            let mut attr = scheduler.attr(tid);
+            attr.nice = prio.clamp(-20, 19) as i8;
+            scheduler.set_attr(tid, attr);
             0
         }
+
+        fn getpriority(&self, _caller: Caller, which: isize, who: isize) -> isize {
+            log::debug!("sys_getpriority <= which: {}, who: {}", which, who);
+            if which != linux_raw_sys::general::PRIO_PROCESS as isize {
+                return -22; // -EINVAL
+            }
+            let tid = match sched_target(who) {
+                Ok(tid) => tid,
+                Err(errno) => return errno,
+            };
+            // 系统调用返回 20 - nice，避免与错误码混淆，由 libc 换算回 nice
+            20 - PROCESSOR.get_mut().manager().scheduler().attr(tid).nice as isize
+        }
+    }
+
+    /// 调度相关系统调用的目标线程，0 表示当前线程。
+    fn sched_target(pid: isize) -> Result<ThreadId, isize> {
+        let processor = PROCESSOR.get_mut();
+        if pid < 0 {
+            return Err(-22); // -EINVAL
+        }
+        if pid == 0 {
+            return Ok(processor.current_tid().unwrap());
+        }
+        let tid = ThreadId::from_usize(pid as usize);
+        match processor.get_task(tid) {
+            Some(_) => Ok(tid),
+            None => Err(-3), // -ESRCH
+        }
+    }
+
+    /// 读取用户传入的 `struct sched_param` 中的 `sched_priority`。
+    fn read_sched_priority(param: usize) -> Option<i32> {
+        let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+        current
+            .address_space
+            .translate_mut::<i32>(VAddr::new(param), READABLE)
+            .map(|ptr| unsafe { *ptr.as_ptr() })
+    }
+
+    /// 设置线程的调度策略，实时策略的优先级为 1..=99，其他策略必须为 0。
+    fn set_sched_policy(tid: ThreadId, policy: SchedPolicy, priority: i32) -> isize {
+        let valid = if policy.is_realtime() {
+            (1..=99).contains(&priority)
+        } else {
+            priority == 0
+        };
+        if !valid {
+            return -22; // -EINVAL
+        }
+        let scheduler = PROCESSOR.get_mut().manager().scheduler();
+        let mut attr = scheduler.attr(tid);
+        attr.policy = policy;
+        attr.rt_priority = priority as u8;
+        scheduler.set_attr(tid, attr);
+        0
+    }
+
+    /// 新线程继承当前线程的调度属性，需要在加入调度队列之前调用。
+    fn inherit_sched_attr(child: ThreadId) {
+        let processor = PROCESSOR.get_mut();
+        let parent = processor.current_tid().unwrap();
+        let scheduler = processor.manager().scheduler();
+        let attr = scheduler.attr(parent);
+        scheduler.set_attr(child, attr);
     }
 
     impl Clock for SyscallContext {
         #[inline]
         fn clock_gettime(&self, _caller: Caller, clock_id: ClockId, tp: usize) -> isize {
             log::debug!("sys_clock_gettime <= clock_id: {:?}, tp: {:#x}", clock_id, tp);
-            const WRITABLE: VmFlags<Sv39> = build_flags("W_V");
-            match clock_id {
-                ClockId::CLOCK_MONOTONIC => {
-                    if let Some(mut ptr) = PROCESSOR
-                        .get_mut()
-                        .get_current_proc()
-                        .unwrap()
-                        .address_space
-                        .translate(VAddr::new(tp), WRITABLE)
-                    {
-                        let time = riscv::register::time::read() * 10000 / 125;
-                        *unsafe { ptr.as_mut() } = TimeSpec {
-                            tv_sec: time / 1_000_000_000,
-                            tv_nsec: time % 1_000_000_000,
-                        };
-                        0
-                    } else {
-                        log::error!("ptr not readable");
-                        -1
-                    }
+            let Some(ns) = clock_now(clock_id) else {
+                return -22; // -EINVAL
+            };
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            match current.address_space.translate_mut(VAddr::new(tp), WRITEABLE) {
+                Some(mut ptr) => {
+                    *unsafe { ptr.as_mut() } = TimeSpec::from_nanosecond(ns);
+                    0
                 }
-                _ => -1,
+                None => -14, // -EFAULT
             }
         }
-    }
 
-    impl Signal for SyscallContext {
-        fn kill(&self, _caller: Caller, pid: isize, signum: u8) -> isize {
-            log::debug!("sys_kill <= pid: {}, signum: {}", pid, signum);
-            if let Some(target_task) = PROCESSOR
-                .get_mut()
-                .get_proc(ProcId::from_usize(pid as usize))
-            {
-                if let Ok(signal_no) = SignalNo::try_from(signum) {
-                    if signal_no != SignalNo::ERR {
-                        target_task.signal.add_signal(signal_no);
-                        return 0;
-                    }
+        fn clock_getres(&self, _caller: Caller, clock_id: ClockId, res: usize) -> isize {
+            log::debug!("sys_clock_getres <= clock_id: {:?}, res: {:#x}", clock_id, res);
+            if clock_now(clock_id).is_none() {
+                return -22; // -EINVAL
+            }
+            if res == 0 {
+                return 0;
+            }
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            match current.address_space.translate_mut(VAddr::new(res), WRITEABLE) {
+                Some(mut ptr) => {
+                    *unsafe { ptr.as_mut() } = TimeSpec::from_nanosecond(timer::resolution_ns());
+                    0
                 }
+                None => -14, // -EFAULT
             }
-            -1
+        }
+
+        fn gettimeofday(&self, _caller: Caller, tv: usize, tz: usize) -> isize {
+            log::debug!("sys_gettimeofday <= tv: {:#x}, tz: {:#x}", tv, tz);
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            if tv != 0 {
+                let Some(mut ptr) = current.address_space.translate_mut(VAddr::new(tv), WRITEABLE) else {
+                    return -14; // -EFAULT
+                };
+                *unsafe { ptr.as_mut() } = TimeVal::from_nanosecond(timer::realtime_ns());
+            }
+            if tz != 0 {
+                // 没有时区，struct timezone 的两个成员都为 0
+                let Some(mut ptr) = current.address_space.translate_mut(VAddr::new(tz), WRITEABLE) else {
+                    return -14; // -EFAULT
+                };
+                *unsafe { ptr.as_mut() } = [0i32; 2];
+            }
+            0
+        }
+
+        fn times(&self, _caller: Caller, buf: usize) -> isize {
+            log::debug!("sys_times <= buf: {:#x}", buf);
+            const NS_PER_TICK: usize = 1_000_000_000 / Tms::CLK_TCK;
+            if buf != 0 {
+                let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+                let Some(mut ptr) = current.address_space.translate_mut(VAddr::new(buf), WRITEABLE) else {
+                    return -14; // -EFAULT
+                };
+                *unsafe { ptr.as_mut() } = Tms {
+                    tms_utime: (current.times.user / NS_PER_TICK) as _,
+                    tms_stime: (current.times.system / NS_PER_TICK) as _,
+                    tms_cutime: (current.children_times.user / NS_PER_TICK) as _,
+                    tms_cstime: (current.children_times.system / NS_PER_TICK) as _,
+                };
+            }
+            // 自启动以来的时钟滴答数
+            (timer::now_ns() / NS_PER_TICK) as isize
+        }
+
+        fn clock_nanosleep(
+            &self,
+            _caller: Caller,
+            clock_id: ClockId,
+            flags: usize,
+            req: usize,
+            rem: usize,
+        ) -> isize {
+            log::debug!(
+                "sys_clock_nanosleep <= clock_id: {:?}, flags: {:#x}, req: {:#x}, rem: {:#x}",
+                clock_id, flags, req, rem
+            );
+            let realtime = match clock_id {
+                ClockId::CLOCK_REALTIME | ClockId::CLOCK_TAI => true,
+                ClockId::CLOCK_MONOTONIC | ClockId::CLOCK_BOOTTIME => false,
+                // 不支持按 CPU 时间睡眠
+                ClockId::CLOCK_PROCESS_CPUTIME_ID => return -95, // -EOPNOTSUPP
+                _ => return -22, // -EINVAL
+            };
+            let ns = match read_timespec(req) {
+                Ok(ns) => ns,
+                Err(errno) => return errno,
+            };
+            if flags & linux_raw_sys::general::TIMER_ABSTIME as usize != 0 {
+                // 绝对时刻的睡眠被打断时不写回剩余时间
+                let deadline = if realtime { timer::realtime_to_monotonic(ns) } else { ns };
+                sleep_until(deadline, 0)
+            } else {
+                sleep_until(timer::now_ns().saturating_add(ns), rem)
+            }
+        }
+    }
+
+    /// 时钟的当前值，单位为纳秒，不支持的时钟返回 `None`。
+    fn clock_now(clock_id: ClockId) -> Option<usize> {
+        Some(match clock_id {
+            ClockId::CLOCK_REALTIME
+            | ClockId::CLOCK_REALTIME_COARSE
+            | ClockId::CLOCK_REALTIME_ALARM
+            | ClockId::CLOCK_TAI => timer::realtime_ns(),
+            // 没有挂起，BOOTTIME 与 MONOTONIC 相同
+            ClockId::CLOCK_MONOTONIC
+            | ClockId::CLOCK_MONOTONIC_RAW
+            | ClockId::CLOCK_MONOTONIC_COARSE
+            | ClockId::CLOCK_BOOTTIME
+            | ClockId::CLOCK_BOOTTIME_ALARM => timer::now_ns(),
+            ClockId::CLOCK_PROCESS_CPUTIME_ID => {
+                PROCESSOR.get_mut().get_current_proc().unwrap().times.total()
+            }
+            ClockId::CLOCK_THREAD_CPUTIME_ID => PROCESSOR.get_mut().current().unwrap().times.total(),
+            _ => return None,
+        })
+    }
+
+    /// execve 的参数和环境变量总长度上限（含指针），用户栈为 512KiB
+    const ARG_MAX: usize = 128 * 1024;
+
+    /// 从用户地址空间读取以 `\0` 结尾的字符串，超过 `max` 字节时返回 `too_long`。
+    fn read_user_cstr(
+        proc: &mut ProcessStruct,
+        addr: usize,
+        max: usize,
+        too_long: isize,
+    ) -> Result<String, isize> {
+        const PAGE_MASK: usize = (1 << Sv39::PAGE_BITS) - 1;
+        let mut bytes = Vec::new();
+        let mut ptr: *const u8 = core::ptr::null();
+        loop {
+            let vaddr = addr + bytes.len();
+            // 字符串可能跨页，每到新的一页重新翻译
+            if bytes.is_empty() || vaddr & PAGE_MASK == 0 {
+                ptr = match proc.address_space.translate_mut::<u8>(VAddr::new(vaddr), READABLE) {
+                    Some(ptr) => ptr.as_ptr(),
+                    None => return Err(-14), // -EFAULT
+                };
+            }
+            let ch = unsafe { *ptr };
+            if ch == 0 {
+                break;
+            }
+            if bytes.len() >= max {
+                return Err(too_long);
+            }
+            bytes.push(ch);
+            ptr = unsafe { ptr.add(1) };
+        }
+        // 只作为字节复制到新程序的用户栈上，不要求是合法的 UTF-8
+        Ok(unsafe { String::from_utf8_unchecked(bytes) })
+    }
+
+    /// 读取以空指针结尾的字符串指针数组，`total` 累计字符串（含 `\0`）和指针占用的字节数。
+    fn read_user_cstr_array(proc: &mut ProcessStruct, addr: usize, total: &mut usize) -> Result<Vec<String>, isize> {
+        let mut strings = Vec::new();
+        // 与 Linux 相同，空指针视为空数组
+        if addr == 0 {
+            return Ok(strings);
+        }
+        loop {
+            let entry = addr + strings.len() * core::mem::size_of::<usize>();
+            let ptr = match proc.address_space.translate_mut::<usize>(VAddr::new(entry), READABLE) {
+                Some(ptr) => unsafe { *ptr.as_ptr() },
+                None => return Err(-14), // -EFAULT
+            };
+            if ptr == 0 {
+                return Ok(strings);
+            }
+            let string = read_user_cstr(proc, ptr, ARG_MAX - *total, -7)?; // -E2BIG
+            *total += string.len() + 1 + core::mem::size_of::<usize>();
+            if *total > ARG_MAX {
+                return Err(-7); // -E2BIG
+            }
+            strings.push(string);
+        }
+    }
+
+    /// 解析文件开头的 `#!` 行，返回解释器路径和可选的一个参数，不是脚本时返回 `None`。
+    ///
+    /// 与 Linux 相同，`#!` 行不超过 256 字节，解释器之后的内容去掉首尾空白后整体作为一个参数。
+    fn parse_shebang(data: &[u8]) -> Option<Result<(String, Option<String>), isize>> {
+        const BINPRM_BUF_SIZE: usize = 256;
+        let rest = data.strip_prefix(b"#!")?;
+        let line = match rest.iter().position(|&ch| ch == b'\n') {
+            Some(end) if 2 + end < BINPRM_BUF_SIZE => &rest[..end],
+            None if data.len() <= BINPRM_BUF_SIZE => rest,
+            _ => return Some(Err(-8)), // -ENOEXEC
+        };
+        let is_blank = |ch: &u8| *ch == b' ' || *ch == b'\t';
+        let start = line.iter().position(|ch| !is_blank(ch)).unwrap_or(line.len());
+        let line = &line[start..];
+        let end = line.iter().position(is_blank).unwrap_or(line.len());
+        let (interp, arg) = line.split_at(end);
+        if interp.is_empty() {
+            return Some(Err(-8)); // -ENOEXEC
+        }
+        let start = arg.iter().position(|ch| !is_blank(ch)).unwrap_or(arg.len());
+        let end = arg.iter().rposition(|ch| !is_blank(ch)).map_or(start, |end| end + 1);
+        let arg = &arg[start..end];
+        let to_string = |bytes: &[u8]| unsafe { String::from_utf8_unchecked(bytes.to_vec()) };
+        Some(Ok((to_string(interp), (!arg.is_empty()).then(|| to_string(arg)))))
+    }
+
+    /// 读取 execve 的路径、参数和环境变量。
+    fn read_exec_args(
+        proc: &mut ProcessStruct,
+        path: usize,
+        argv: usize,
+        envp: usize,
+    ) -> Result<(String, Vec<String>, Vec<String>), isize> {
+        let path_max = linux_raw_sys::general::PATH_MAX as usize;
+        let name = read_user_cstr(proc, path, path_max, -36)?; // -ENAMETOOLONG
+        let mut total = 0;
+        let args = read_user_cstr_array(proc, argv, &mut total)?;
+        let envs = read_user_cstr_array(proc, envp, &mut total)?;
+        Ok((name, args, envs))
+    }
+
+    /// futex 字 `uaddr` 的等待队列键，地址无效时返回 `None`。
+    ///
+    /// 私有 futex 和私有映射中的 futex 按 (pid, uaddr) 区分，写时复制和换出都不会改变键；
+    /// 共享映射中的非私有 futex 按物理地址区分，以便映射同一页的进程互相唤醒。
+    fn futex_key(proc: &mut ProcessStruct, uaddr: usize, private: bool) -> Option<FutexKey> {
+        let addr = VAddr::new(uaddr);
+        if private || !proc.address_space.is_shared(addr) {
+            // 地址须已映射
+            proc.address_space.translate_mut::<u32>(addr, READABLE)?;
+            return Some(FutexKey::Private(proc.pid.get_usize(), uaddr));
+        }
+        // 内核恒等映射物理内存，翻译得到的指针就是 futex 字的物理地址
+        let ptr = proc.address_space.translate_mut::<u32>(addr, READABLE)?;
+        Some(FutexKey::Shared(ptr.as_ptr() as usize))
+    }
+
+    /// 读取用户传入的 `timespec`，返回纳秒数，`tv_sec` 为负或 `tv_nsec` 不在 `0..1_000_000_000` 中时返回 -EINVAL。
+    fn read_timespec(ptr: usize) -> Result<usize, isize> {
+        let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+        let Some(ts) = current
+            .address_space
+            .translate_mut::<TimeSpec>(VAddr::new(ptr), READABLE)
+        else {
+            return Err(-14); // -EFAULT
+        };
+        let ts = unsafe { ts.as_ref() };
+        // tv_sec 按有符号数检查，不能为负
+        if (ts.tv_sec as isize) < 0 || ts.tv_nsec >= 1_000_000_000 {
+            return Err(-22); // -EINVAL
+        }
+        // 超出范围的时间按最大值处理，相当于永不超时
+        Ok(ts.tv_sec.saturating_mul(1_000_000_000).saturating_add(ts.tv_nsec))
+    }
+
+    /// 当前线程睡眠到 `deadline`，`rem` 非 0 时被信号打断后写回剩余时间。
+    fn sleep_until(deadline: usize, rem: usize) -> isize {
+        if deadline <= timer::now_ns() {
+            return 0;
+        }
+        let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+        // 先检查 `rem` 可写，被打断时再按虚拟地址写回
+        if rem != 0
+            && current
+                .address_space
+                .translate_mut::<TimeSpec>(VAddr::new(rem), WRITEABLE)
+                .is_none()
+        {
+            return -14; // -EFAULT
+        }
+        let pid = current.pid;
+        let tid = PROCESSOR.get_mut().current_tid().unwrap();
+        sleep::sleep(pid, tid, deadline, rem);
+        // 由 trap 循环阻塞当前线程
+        BLOCKED
+    }
+
+    /// 信号按目标进程当前的处理方式是否会结束它：SIGKILL，或处理方式为默认且默认行为是结束进程。
+    fn is_fatal(target: &ProcessStruct, signal_no: SignalNo) -> bool {
+        match signal_no {
+            SignalNo::SIGKILL => true,
+            SignalNo::SIGSTOP | SignalNo::SIGCONT | SignalNo::SIGCHLD | SignalNo::SIGURG => false,
+            _ => target
+                .signal
+                .get_action_ref(signal_no)
+                .is_some_and(|action| action.handler == tg_signal::SIG_DFL),
+        }
+    }
+
+    /// 让线程 `tid` 退出它所在的等待：睡眠、futex、条件变量、互斥锁和信号量的等待返回 -EINTR，
+    /// wait4 被唤醒后重新执行。线程回到用户态后的下一次系统调用处理信号。
+    fn interrupt_wait(target: &mut ProcessStruct, tid: ThreadId) {
+        if sleep::interrupt(tid) {
+            return;
+        }
+        // wait4 阻塞时 pc 已回退到 ecall，a0 仍是参数，不能改写返回值
+        if cancel_child_wait(tid) {
+            wake_thread(tid);
+            return;
+        }
+        let cancelled = futex::cancel_wait(tid)
+            || target.condvar_list.iter().flatten().any(|condvar| condvar.cancel_wait(tid).is_some())
+            || target.mutex_list.iter().flatten().any(|mutex| mutex.cancel_wait(tid))
+            || target.semaphore_list.iter().flatten().any(|sem| sem.cancel_wait(tid));
+        if cancelled {
+            set_blocked_ret(tid, -4); // -EINTR
+            wake_thread(tid);
+        }
+    }
+
+    /// 向进程 `pid` 发送信号。
+    fn send_signal(pid: ProcId, signal_no: SignalNo) {
+        let processor = PROCESSOR.get_mut();
+        let Some(target) = processor.get_proc(pid) else {
+            return;
+        };
+        target.signal.add_signal(signal_no);
+        // 打断目标进程中正在睡眠的线程，信号在它下一次系统调用之后处理；
+        // 会结束进程的信号还要打断其他等待，否则阻塞的进程永远不会结束
+        let fatal = is_fatal(target, signal_no);
+        if let Some(threads) = PROCESSOR.get_mut().get_thread(pid).cloned() {
+            for tid in threads {
+                if fatal {
+                    interrupt_wait(target, tid);
+                } else {
+                    sleep::interrupt(tid);
+                }
+            }
+        }
+        // 恢复被 SIGSTOP 停止的线程，并向父进程报告
+        if signal_no == SignalNo::SIGCONT && !target.stopped_threads.is_empty() {
+            for tid in core::mem::take(&mut target.stopped_threads) {
+                wake_thread(tid);
+            }
+            PROCESSOR.get_mut().child_continued(pid);
+            notify_child_event();
+        }
+    }
+
+    impl Signal for SyscallContext {
+        fn kill(&self, _caller: Caller, pid: isize, signum: u8) -> isize {
+            log::debug!("sys_kill <= pid: {}, signum: {}", pid, signum);
+            // 信号 0 只检查目标进程是否存在
+            let signal_no = match SignalNo::try_from(signum) {
+                Ok(SignalNo::ERR) if signum == 0 => None,
+                Ok(signal_no) if signal_no != SignalNo::ERR => Some(signal_no),
+                _ => return -22, // -EINVAL
+            };
+            let processor = PROCESSOR.get_mut();
+            let current = processor.get_current_proc().unwrap().pid;
+            // pid > 0 为指定进程，0 为当前进程组，-1 为除 init 和自己以外的所有进程，< -1 为进程组 -pid
+            let targets: Vec<ProcId> = match pid {
+                0 => {
+                    let pgid = processor.get_pgid(current).unwrap();
+                    processor.procs_in_group(pgid).collect()
+                }
+                -1 => {
+                    let init = processor.init_proc();
+                    processor
+                        .procs()
+                        .filter(|&id| id != current && Some(id) != init)
+                        .collect()
+                }
+                pid if pid < 0 => match pid.checked_neg() {
+                    Some(pgid) => processor.procs_in_group(ProcId::from_usize(pgid as usize)).collect(),
+                    None => return -3, // -ESRCH
+                },
+                pid => processor
+                    .get_proc(ProcId::from_usize(pid as usize))
+                    .map(|proc| vec![proc.pid])
+                    .unwrap_or_default(),
+            };
+            if targets.is_empty() {
+                return -3; // -ESRCH
+            }
+            if let Some(signal_no) = signal_no {
+                for target in targets {
+                    send_signal(target, signal_no);
+                }
+            }
+            0
         }
 
         fn sigaction(
@@ -900,7 +2017,7 @@ mod impls {
                 if old_action as usize != 0 {
                     if let Some(mut ptr) = current
                         .address_space
-                        .translate(VAddr::new(old_action), WRITEABLE)
+                        .translate_mut(VAddr::new(old_action), WRITEABLE)
                     {
                         if let Some(signal_action) = current.signal.get_action_ref(signal_no) {
                             *unsafe { ptr.as_mut() } = signal_action;
@@ -916,15 +2033,20 @@ mod impls {
                 if action as usize != 0 {
                     if let Some(ptr) = current
                         .address_space
-                        .translate(VAddr::new(action), READABLE)
+                        .translate_mut(VAddr::new(action), READABLE)
                     {
+                        let new_action: tg_signal::SignalAction = unsafe { *ptr.as_ptr() };
                         // 如果返回了 false，说明 signal_no 无效
-                        if !current
-                            .signal
-                            .set_action(signal_no, &unsafe { *ptr.as_ptr() })
-                        {
+                        if !current.signal.set_action(signal_no, &new_action) {
                             return -1;
                         }
+                        // 忽略 SIGCHLD 的进程不保留僵尸，子进程结束后直接回收
+                        if signal_no == SignalNo::SIGCHLD {
+                            let pid = current.pid;
+                            PROCESSOR
+                                .get_mut()
+                                .set_auto_reap(pid, new_action.handler == tg_signal::SIG_IGN);
+                        }
                     } else {
                         return -1;
                     }
@@ -963,6 +2085,350 @@ mod impls {
         }
     }
 
+    impl tg_syscall::Thread for SyscallContext {
+        fn thread_create(&self, _caller: Caller, entry: usize, arg: usize) -> isize {
+            log::debug!("sys_thread_create <= entry: {:#x}, arg: {:#x}", entry, arg);
+            const STACK_PAGES: usize = 16;
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            // 从主线程栈之下开始寻找空闲区域，每个线程栈下方留一页保护页
+            let mut top = USER_TOP_VPN - USER_STACK_PAGES - 1;
+            while current
+                .address_space
+                .areas
+                .iter()
+                .any(|area| area.start.val() < top && top - STACK_PAGES < area.end.val())
+            {
+                top -= STACK_PAGES + 1;
+            }
+            current
+                .address_space
+                .map_lazy(VPN::new(top - STACK_PAGES)..VPN::new(top), build_flags("U_WRV"));
+            let mut context = tg_kernel_context::LocalContext::user(entry);
+            *context.sp_mut() = VPN::<Sv39>::new(top).base().val();
+            *context.a_mut(0) = arg;
+            let thread = current.new_thread(context);
+            let (pid, tid) = (current.pid, thread.tid);
+            inherit_sched_attr(tid);
+            PROCESSOR.get_mut().add(tid, thread, pid);
+            tid.get_usize() as isize
+        }
+
+        fn gettid(&self, _caller: Caller) -> isize {
+            log::debug!("sys_gettid <=");
+            PROCESSOR.get_mut().current().unwrap().tid.get_usize() as isize
+        }
+
+        fn waittid(&self, _caller: Caller, tid: usize) -> isize {
+            log::debug!("sys_waittid <= tid: {}", tid);
+            let processor = PROCESSOR.get_mut();
+            // 线程不能等待自己
+            if processor.current().unwrap().tid.get_usize() == tid {
+                return -1;
+            }
+            // 线程仍在运行时返回 -2，由用户态重试
+            processor.waittid(ThreadId::from_usize(tid)).unwrap_or(-1)
+        }
+    }
+
+    impl SyncMutex for SyscallContext {
+        fn semaphore_create(&self, _caller: Caller, res_count: usize) -> isize {
+            log::debug!("sys_semaphore_create <= res_count: {}", res_count);
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            current
+                .semaphore_list
+                .push(Some(Arc::new(Semaphore::new(res_count))));
+            current.semaphore_detector.add_resource(res_count);
+            current.semaphore_list.len() as isize - 1
+        }
+
+        fn semaphore_up(&self, _caller: Caller, sem_id: usize) -> isize {
+            log::debug!("sys_semaphore_up <= sem_id: {}", sem_id);
+            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+            let tid = unsafe { (*processor).current().unwrap().tid };
+            let current = unsafe { (*processor).get_current_proc().unwrap() };
+            let Some(Some(sem)) = current.semaphore_list.get(sem_id) else {
+                return -22; // -EINVAL
+            };
+            current.semaphore_detector.release(tid, sem_id);
+            if let Some(waking_tid) = sem.up() {
+                current.semaphore_detector.acquire(waking_tid, sem_id);
+                wake_thread(waking_tid);
+            }
+            0
+        }
+
+        fn semaphore_down(&self, _caller: Caller, sem_id: usize) -> isize {
+            log::debug!("sys_semaphore_down <= sem_id: {}", sem_id);
+            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+            let tid = unsafe { (*processor).current().unwrap().tid };
+            let current = unsafe { (*processor).get_current_proc().unwrap() };
+            let Some(Some(sem)) = current.semaphore_list.get(sem_id) else {
+                return -22; // -EINVAL
+            };
+            if !current.deadlock_detect {
+                current.semaphore_detector.add_need(tid, sem_id);
+            } else if !current.semaphore_detector.request(tid, sem_id) {
+                return -0xDEAD;
+            }
+            // 获取失败时由 trap 循环阻塞当前线程；申请记在 Need 中，直到被唤醒
+            if sem.down(tid) {
+                current.semaphore_detector.acquire(tid, sem_id);
+                0
+            } else {
+                BLOCKED
+            }
+        }
+
+        fn mutex_create(&self, _caller: Caller, blocking: bool) -> isize {
+            log::debug!("sys_mutex_create <= blocking: {}", blocking);
+            // 单处理器上自旋锁没有意义，都创建为阻塞锁
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            let mutex: Arc<dyn MutexTrait> = Arc::new(MutexBlocking::new());
+            current.mutex_list.push(Some(mutex));
+            current.mutex_detector.add_resource(1);
+            current.mutex_list.len() as isize - 1
+        }
+
+        fn mutex_unlock(&self, _caller: Caller, mutex_id: usize) -> isize {
+            log::debug!("sys_mutex_unlock <= mutex_id: {}", mutex_id);
+            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+            let tid = unsafe { (*processor).current().unwrap().tid };
+            let current = unsafe { (*processor).get_current_proc().unwrap() };
+            let Some(Some(mutex)) = current.mutex_list.get(mutex_id) else {
+                return -22; // -EINVAL
+            };
+            // 只有持有锁的线程可以释放它
+            if mutex.owner() != Some(tid) {
+                return -1; // -EPERM
+            }
+            current.mutex_detector.release(tid, mutex_id);
+            // 锁直接交给被唤醒的线程
+            if let Some(waking_tid) = mutex.unlock() {
+                current.mutex_detector.acquire(waking_tid, mutex_id);
+                wake_thread(waking_tid);
+            }
+            0
+        }
+
+        fn mutex_lock(&self, _caller: Caller, mutex_id: usize) -> isize {
+            log::debug!("sys_mutex_lock <= mutex_id: {}", mutex_id);
+            let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+            let tid = unsafe { (*processor).current().unwrap().tid };
+            let current = unsafe { (*processor).get_current_proc().unwrap() };
+            let Some(Some(mutex)) = current.mutex_list.get(mutex_id).cloned() else {
+                return -22; // -EINVAL
+            };
+            if !current.deadlock_detect {
+                current.mutex_detector.add_need(tid, mutex_id);
+            } else if !current.mutex_detector.request(tid, mutex_id) {
+                return -0xDEAD;
+            }
+            // 获取失败时由 trap 循环阻塞当前线程；申请记在 Need 中，直到被唤醒
+            if lock_mutex(current, tid, mutex_id, &mutex) {
+                0
+            } else {
+                BLOCKED
+            }
+        }
+
+        fn condvar_create(&self, _caller: Caller, _arg: usize) -> isize {
+            log::debug!("sys_condvar_create <=");
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            current.condvar_list.push(Some(Arc::new(Condvar::new())));
+            current.condvar_list.len() as isize - 1
+        }
+
+        fn condvar_signal(&self, _caller: Caller, condvar_id: usize) -> isize {
+            log::debug!("sys_condvar_signal <= condvar_id: {}", condvar_id);
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            let Some(Some(condvar)) = current.condvar_list.get(condvar_id).cloned() else {
+                return -22; // -EINVAL
+            };
+            if let Some((tid, mutex)) = condvar.signal() {
+                relock(current, tid, mutex);
+            }
+            0
+        }
+
+        fn condvar_broadcast(&self, _caller: Caller, condvar_id: usize) -> isize {
+            log::debug!("sys_condvar_broadcast <= condvar_id: {}", condvar_id);
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            let Some(Some(condvar)) = current.condvar_list.get(condvar_id).cloned() else {
+                return -22; // -EINVAL
+            };
+            for (tid, mutex) in condvar.broadcast() {
+                relock(current, tid, mutex);
+            }
+            0
+        }
+
+        fn condvar_wait(&self, _caller: Caller, condvar_id: usize, mutex_id: usize) -> isize {
+            log::debug!("sys_condvar_wait <= condvar_id: {}, mutex_id: {}", condvar_id, mutex_id);
+            condvar_wait_until(condvar_id, mutex_id, None)
+        }
+
+        fn condvar_timedwait(
+            &self,
+            _caller: Caller,
+            condvar_id: usize,
+            mutex_id: usize,
+            abstime: usize,
+        ) -> isize {
+            log::debug!(
+                "sys_condvar_timedwait <= condvar_id: {}, mutex_id: {}, abstime: {:#x}",
+                condvar_id, mutex_id, abstime
+            );
+            match read_timespec(abstime) {
+                Ok(deadline) => condvar_wait_until(condvar_id, mutex_id, Some(deadline)),
+                Err(errno) => errno,
+            }
+        }
+
+        fn enable_deadlock_detect(&self, _caller: Caller, is_enable: i32) -> isize {
+            log::debug!("sys_enable_deadlock_detect <= is_enable: {}", is_enable);
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            // 资源的分配情况总是被记录，开关只决定申请时是否做安全性检查
+            match is_enable {
+                0 => current.deadlock_detect = false,
+                1 => current.deadlock_detect = true,
+                _ => return -22, // -EINVAL
+            }
+            0
+        }
+    }
+
+    /// 释放互斥锁并阻塞在条件变量上，`deadline` 为单调时钟上的超时时刻。
+    fn condvar_wait_until(condvar_id: usize, mutex_id: usize, deadline: Option<usize>) -> isize {
+        let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+        let tid = unsafe { (*processor).current().unwrap().tid };
+        let current = unsafe { (*processor).get_current_proc().unwrap() };
+        let (Some(Some(condvar)), Some(Some(mutex))) = (
+            current.condvar_list.get(condvar_id).cloned(),
+            current.mutex_list.get(mutex_id).cloned(),
+        ) else {
+            return -22; // -EINVAL
+        };
+        // 等待前须持有互斥锁
+        if mutex.owner() != Some(tid) {
+            return -1; // -EPERM
+        }
+        current.mutex_detector.release(tid, mutex_id);
+        if let Some(waking_tid) = condvar.wait_with_mutex(tid, mutex) {
+            current.mutex_detector.acquire(waking_tid, mutex_id);
+            wake_thread(waking_tid);
+        }
+        if let Some(deadline) = deadline {
+            let pid = current.pid;
+            timer::set_timeout(tid, deadline, move || {
+                if let Some(mutex) = condvar.cancel_wait(tid) {
+                    set_blocked_ret(tid, -110); // -ETIMEDOUT
+                    if let Some(current) = PROCESSOR.get_mut().get_proc(pid) {
+                        relock(current, tid, mutex);
+                    }
+                }
+            });
+        }
+        // 由 trap 循环阻塞当前线程
+        BLOCKED
+    }
+
+    /// 线程 `tid` 竞争互斥锁 `mutex_id`，申请已记入 Need，获得锁时转入 Allocation 并返回 true。
+    ///
+    /// 没有获得锁时线程已经进入互斥锁的等待队列，由 `mutex_unlock` 把锁交给它。
+    fn lock_mutex(current: &mut ProcessStruct, tid: ThreadId, mutex_id: usize, mutex: &Arc<dyn MutexTrait>) -> bool {
+        if mutex.lock(tid) {
+            current.mutex_detector.acquire(tid, mutex_id);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 让从条件变量上醒来的线程重新竞争互斥锁，与 `mutex_lock` 一样记入死锁检测器，但不能拒绝。
+    ///
+    /// 获得锁时立即唤醒它，否则它已经进入互斥锁的等待队列，由 `mutex_unlock` 唤醒。
+    fn relock(current: &mut ProcessStruct, tid: ThreadId, mutex: Arc<dyn MutexTrait>) {
+        let Some(mutex_id) = current
+            .mutex_list
+            .iter()
+            .position(|m| m.as_ref().is_some_and(|m| Arc::ptr_eq(m, &mutex)))
+        else {
+            return;
+        };
+        current.mutex_detector.add_need(tid, mutex_id);
+        if lock_mutex(current, tid, mutex_id, &mutex) {
+            wake_thread(tid);
+        }
+    }
+
+    /// mmap 区域的最高页号，之上的 256MiB 留给主线程栈和线程栈
+    const MMAP_TOP_VPN: usize = USER_TOP_VPN - (1 << 16);
+    /// mmap 可用的最低页号，与 Linux 的 `mmap_min_addr` 默认值相同
+    const MMAP_MIN_VPN: usize = 0x10;
+
+    /// 从 `start` 开始的 `pages` 页是否与已有的虚拟地址块都不重叠。
+    fn mmap_area_free(space: &AddressSpace<Sv39, Sv39Manager>, start: usize, pages: usize) -> bool {
+        space
+            .areas
+            .iter()
+            .all(|area| area.end.val() <= start || start + pages <= area.start.val())
+    }
+
+    /// 在 mmap 区域中自顶向下查找 `pages` 页的空闲范围，返回起始页号。
+    fn find_mmap_area(space: &AddressSpace<Sv39, Sv39Manager>, pages: usize) -> Option<usize> {
+        let mut areas: Vec<(usize, usize)> = space
+            .areas
+            .iter()
+            .map(|area| (area.start.val(), area.end.val()))
+            .filter(|&(start, _)| start < MMAP_TOP_VPN)
+            .collect();
+        // 按结束页号从高到低，依次检查每个区域之上的空隙
+        areas.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+        let mut end = MMAP_TOP_VPN;
+        for (start, area_end) in areas {
+            if area_end <= end && end - area_end >= pages {
+                return Some(end - pages);
+            }
+            end = end.min(start);
+        }
+        (end >= MMAP_MIN_VPN + pages).then(|| end - pages)
+    }
+
+    /// `mmap`/`mprotect` 允许的保护标志
+    const PROT_MASK: u32 = linux_raw_sys::general::PROT_READ
+        | linux_raw_sys::general::PROT_WRITE
+        | linux_raw_sys::general::PROT_EXEC;
+
+    /// 把 `PROT_*` 转换为用户页的页属性。
+    fn prot_flags(prot: u32) -> VmFlags<Sv39> {
+        use linux_raw_sys::general::{PROT_EXEC, PROT_READ, PROT_WRITE};
+        let mut flags: [u8; 5] = *b"U___V";
+        if prot & PROT_EXEC != 0 {
+            flags[1] = b'X';
+        }
+        if prot & PROT_WRITE != 0 {
+            flags[2] = b'W';
+        }
+        // RISC-V 不允许可写不可读的页
+        if prot & (PROT_READ | PROT_WRITE) != 0 {
+            flags[3] = b'R';
+        }
+        parse_flags(unsafe { core::str::from_utf8_unchecked(&flags) }).unwrap()
+    }
+
+    /// 页号范围 `[start, end)` 是否完全被已有的虚拟地址块覆盖。
+    fn area_mapped(space: &AddressSpace<Sv39, Sv39Manager>, start: usize, end: usize) -> bool {
+        let mut covered = start;
+        let mut areas: Vec<_> = space.areas.iter().collect();
+        areas.sort_unstable_by_key(|area| area.start.val());
+        for area in areas {
+            if area.start.val() <= covered && covered < area.end.val() {
+                covered = area.end.val();
+            }
+        }
+        covered >= end
+    }
+
     impl Memory for SyscallContext {
         fn brk(&self, _caller: Caller, addr: usize) -> isize {
             log::debug!("sys_brk <= addr: {:#x}", addr);
@@ -982,9 +2448,9 @@ mod impls {
                 return current.heap_end as isize;  // 失败，返回当前边界
             }
             
-            // 确保请求的地址不会与栈冲突（简单检查：不超过某个上限）
-            const MAX_HEAP_ADDR: usize = 0x10000000;  // 256MB 堆上限
-            if addr > MAX_HEAP_ADDR {
+            // 确保请求的地址不会与栈冲突（简单检查：堆不超过某个上限）
+            const MAX_HEAP_SIZE: usize = 0x10000000;  // 256MB 堆上限
+            if addr - current.heap_start > MAX_HEAP_SIZE {
                 return current.heap_end as isize;  // 失败，返回当前边界
             }
             
@@ -997,28 +2463,29 @@ mod impls {
                 let old_heap_end_page = (old_heap_end + PAGE_SIZE - 1) / PAGE_SIZE;
                 let new_heap_end_page = (new_heap_end + PAGE_SIZE - 1) / PAGE_SIZE;
                 
-                // 需要映射新页
+                // 需要映射新页，物理页在首次访问时分配
                 if new_heap_end_page > old_heap_end_page {
-                    let pages_to_map = new_heap_end_page - old_heap_end_page;
+                    // 不能覆盖 mmap 等已有的映射
+                    let pages = new_heap_end_page - old_heap_end_page;
+                    if !mmap_area_free(&current.address_space, old_heap_end_page, pages) {
+                        return current.heap_end as isize;  // 失败，返回当前边界
+                    }
                     let start_vpn = VPN::new(old_heap_end_page);
                     let end_vpn = VPN::new(new_heap_end_page);
-                    
-                    // 分配物理内存
-                    let layout = Layout::from_size_align(
-                        pages_to_map * PAGE_SIZE,
-                        PAGE_SIZE,
-                    ).unwrap();
-                    let ptr = unsafe { alloc_zeroed(layout) };
-                    
-                    // 映射到地址空间
-                    current.address_space.map_extern(
-                        start_vpn..end_vpn,
-                        PPN::new(ptr as usize >> Sv39::PAGE_BITS),
-                        build_flags("U_WRV"),
-                    );
+                    current.address_space.map_lazy(start_vpn..end_vpn, build_flags("U_WRV"));
+                }
+            }
+            // 如果新地址比旧地址小，取消多出的页的映射，之后再扩展时重新按需分配
+            if new_heap_end < old_heap_end {
+                const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+                let old_heap_end_page = old_heap_end.div_ceil(PAGE_SIZE);
+                let new_heap_end_page = new_heap_end.div_ceil(PAGE_SIZE);
+                if new_heap_end_page < old_heap_end_page {
+                    current
+                        .address_space
+                        .unmap(VPN::new(new_heap_end_page)..VPN::new(old_heap_end_page));
                 }
             }
-            // 如果新地址比旧地址小，理论上应该释放页面，但我们简化处理，不实际释放
             
             // 更新堆边界
             current.heap_end = new_heap_end;
@@ -1043,7 +2510,7 @@ mod impls {
             for i in 0..len {
                 if let Some(mut ptr) = current
                     .address_space
-                    .translate::<u8>(VAddr::new(buf + i), WRITABLE)
+                    .translate_mut::<u8>(VAddr::new(buf + i), WRITABLE)
                 {
                     unsafe {
                         // 简单的线性同余生成器
@@ -1061,49 +2528,185 @@ mod impls {
         }
         
         fn mprotect(&self, _caller: Caller, addr: usize, len: usize, prot: i32) -> isize {
-            log::debug!("sys_mprotect <= addr: {:#x}, len: {:#x}, prot: {}", addr, len, prot);
-            // 简化实现：只检查参数有效性，不实际修改页表属性
-            // 完整实现需要修改页表条目的权限位
-            
-            // 检查地址是否对齐
+            log::debug!("sys_mprotect <= addr: {:#x}, len: {:#x}, prot: {:#x}", addr, len, prot);
             const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
-            if addr % PAGE_SIZE != 0 {
+            let prot = prot as u32;
+            // 检查地址是否对齐、保护标志是否有效（不支持 PROT_GROWSDOWN/PROT_GROWSUP）
+            if addr & (PAGE_SIZE - 1) != 0 || prot & !PROT_MASK != 0 {
                 return -22; // -EINVAL
             }
-            
-            // 检查保护标志是否有效（0-7 是有效的组合）
-            if prot < 0 || prot > 7 {
-                return -22; // -EINVAL
+            let start = addr >> Sv39::PAGE_BITS;
+            let Some(end) = addr
+                .checked_add(len)
+                .and_then(|end| end.checked_add(PAGE_SIZE - 1))
+                .map(|end| end >> Sv39::PAGE_BITS)
+            else {
+                return -12; // -ENOMEM
+            };
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            // 范围内不能有未映射的页
+            if !area_mapped(&current.address_space, start, end) {
+                return -12; // -ENOMEM
+            }
+            if start < end {
+                current
+                    .address_space
+                    .protect(VPN::new(start)..VPN::new(end), prot_flags(prot));
             }
-            
-            // 检查区域是否有效（简化：不真正检查）
-            let _ = len;
-            
-            // 简化实现：直接返回成功
-            // 实际应该修改地址空间中对应页的权限
             0
         }
 
         fn mmap(
             &self,
             _caller: Caller,
-            _addr: usize,
-            _length: usize,
-            _prot: i32,
-            _flags: i32,
-            _fd: i32,
-            _offset: usize,
+            addr: usize,
+            length: usize,
+            prot: i32,
+            flags: i32,
+            fd: i32,
+            offset: usize,
         ) -> isize {
-            log::debug!("sys_mmap <= addr: {:#x}, length: {:#x}, prot: {}, flags: {}, fd: {}, offset: {:#x}", 
-                       _addr, _length, _prot, _flags, _fd, _offset);
-            // 暂不实现，返回错误
-            -1
+            use linux_raw_sys::general::{
+                MAP_ANONYMOUS, MAP_FIXED, MAP_FIXED_NOREPLACE, MAP_PRIVATE, MAP_SHARED,
+                MAP_SHARED_VALIDATE, PROT_EXEC, PROT_READ, PROT_WRITE,
+            };
+            const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+            log::debug!(
+                "sys_mmap <= addr: {:#x}, length: {:#x}, prot: {:#x}, flags: {:#x}, fd: {}, offset: {:#x}",
+                addr, length, prot, flags, fd, offset
+            );
+            let (prot, flags) = (prot as u32, flags as u32);
+            if length == 0 || offset & (PAGE_SIZE - 1) != 0 {
+                return -22; // -EINVAL
+            }
+            if prot & !PROT_MASK != 0 {
+                return -22; // -EINVAL
+            }
+            let Some(pages) = length.checked_add(PAGE_SIZE - 1).map(|len| len >> Sv39::PAGE_BITS) else {
+                return -12; // -ENOMEM
+            };
+            // 映射类型在低 4 位
+            let shared = match flags & 0xf {
+                MAP_PRIVATE => false,
+                MAP_SHARED | MAP_SHARED_VALIDATE => true,
+                _ => return -22, // -EINVAL
+            };
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            // 文件映射的页从文件读入，私有映射写时复制，共享映射的修改写回文件
+            let source: Option<Arc<dyn PageSource>> = if flags & MAP_ANONYMOUS != 0 {
+                None
+            } else {
+                let Some(Some(file)) = current.fd_table.get(fd as usize) else {
+                    return -9; // -EBADF
+                };
+                let entry = file.lock();
+                let Fd::File(file) = &*entry else {
+                    return -19; // -ENODEV
+                };
+                let Some(inode) = file.inode.clone() else {
+                    return -19; // -ENODEV
+                };
+                if !file.readable() || (shared && prot & PROT_WRITE != 0 && !file.writable()) {
+                    return -13; // -EACCES
+                }
+                Some(Arc::new(FileSource { inode, offset }))
+            };
+            let space = &mut current.address_space;
+            let fixed = flags & (MAP_FIXED | MAP_FIXED_NOREPLACE) != 0;
+            let start = if fixed {
+                if addr & (PAGE_SIZE - 1) != 0 {
+                    return -22; // -EINVAL
+                }
+                let start = addr >> Sv39::PAGE_BITS;
+                if start < MMAP_MIN_VPN || USER_TOP_VPN.checked_sub(start).is_none_or(|room| pages > room) {
+                    return -12; // -ENOMEM
+                }
+                if !mmap_area_free(space, start, pages) {
+                    if flags & MAP_FIXED_NOREPLACE != 0 {
+                        return -17; // -EEXIST
+                    }
+                    // MAP_FIXED 替换原有的映射
+                    space.unmap(VPN::new(start)..VPN::new(start + pages));
+                }
+                start
+            } else {
+                // 地址只是提示，可用时采用，否则在 mmap 区域中自顶向下查找
+                let hint = addr >> Sv39::PAGE_BITS;
+                if hint >= MMAP_MIN_VPN
+                    && USER_TOP_VPN.checked_sub(hint).is_some_and(|room| pages <= room)
+                    && mmap_area_free(space, hint, pages)
+                {
+                    hint
+                } else {
+                    match find_mmap_area(space, pages) {
+                        Some(start) => start,
+                        None => return -12, // -ENOMEM
+                    }
+                }
+            };
+            // 物理页在首次访问时分配，MAP_NORESERVE 不需要额外处理
+            let range = VPN::new(start)..VPN::new(start + pages);
+            space.map_backed(range.clone(), prot_flags(prot), source, shared);
+            // 共享映射在 fork 前就要有物理页，父子进程才能看到彼此的修改；PROT_NONE 的区域不分配
+            if shared && prot & (PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+                let mut vpn = range.start;
+                while vpn < range.end {
+                    if !space.populate(vpn.base()) {
+                        space.unmap(range);
+                        return -12; // -ENOMEM
+                    }
+                    vpn = vpn + 1;
+                }
+            }
+            (start << Sv39::PAGE_BITS) as isize
         }
 
-        fn munmap(&self, _caller: Caller, _addr: usize, _length: usize) -> isize {
-            log::debug!("sys_munmap <= addr: {:#x}, length: {:#x}", _addr, _length);
-            // 暂不实现，返回错误
-            -1
+        fn munmap(&self, _caller: Caller, addr: usize, length: usize) -> isize {
+            const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+            log::debug!("sys_munmap <= addr: {:#x}, length: {:#x}", addr, length);
+            if addr & (PAGE_SIZE - 1) != 0 || length == 0 {
+                return -22; // -EINVAL
+            }
+            let start = addr >> Sv39::PAGE_BITS;
+            let Some(pages) = length.checked_add(PAGE_SIZE - 1).map(|len| len >> Sv39::PAGE_BITS) else {
+                return -22; // -EINVAL
+            };
+            if pages > USER_TOP_VPN.saturating_sub(start) {
+                return -22; // -EINVAL
+            }
+            // 与 Linux 相同，范围内没有映射的部分直接忽略，部分覆盖的区域被拆分
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            current
+                .address_space
+                .unmap(VPN::new(start)..VPN::new(start + pages));
+            0
+        }
+
+        fn msync(&self, _caller: Caller, addr: usize, length: usize, flags: u32) -> isize {
+            use linux_raw_sys::general::{MS_ASYNC, MS_INVALIDATE, MS_SYNC};
+            const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+            log::debug!("sys_msync <= addr: {:#x}, length: {:#x}, flags: {:#x}", addr, length, flags);
+            if addr & (PAGE_SIZE - 1) != 0
+                || flags & !(MS_ASYNC | MS_INVALIDATE | MS_SYNC) != 0
+                || flags & (MS_ASYNC | MS_SYNC) == MS_ASYNC | MS_SYNC
+            {
+                return -22; // -EINVAL
+            }
+            let start = addr >> Sv39::PAGE_BITS;
+            let Some(end) = addr
+                .checked_add(length)
+                .and_then(|end| end.checked_add(PAGE_SIZE - 1))
+                .map(|end| end >> Sv39::PAGE_BITS)
+            else {
+                return -12; // -ENOMEM
+            };
+            let current = PROCESSOR.get_mut().get_current_proc().unwrap();
+            if !area_mapped(&current.address_space, start, end) {
+                return -12; // -ENOMEM
+            }
+            // 写回是同步完成的，MS_ASYNC 与 MS_SYNC 相同
+            current.address_space.sync(VPN::new(start)..VPN::new(end));
+            0
         }
     }
 
@@ -1,29 +1,85 @@
 use crate::{
-    build_flags, fs::Fd, map_portal, parse_flags, processor::ProcessorInner, Sv39, Sv39Manager,
-    PROCESSOR,
+    build_flags,
+    fs::{read_all, Fd, FS},
+    map_portal, parse_flags, processor::ProcessorInner,
+    user_stack::{self, UserStack},
+    Sv39, Sv39Manager, PROCESSOR,
+};
+use alloc::{boxed::Box, collections::BTreeSet, string::String, sync::Arc, vec::Vec};
+use linux_raw_sys::auxvec::{
+    AT_BASE, AT_CLKTCK, AT_EGID, AT_ENTRY, AT_EUID, AT_GID, AT_HWCAP, AT_PAGESZ, AT_PHDR, AT_PHENT,
+    AT_PHNUM, AT_SECURE, AT_UID,
 };
-use alloc::{alloc::alloc_zeroed, boxed::Box, vec::Vec, string::String};
-use core::alloc::Layout;
 use spin::Mutex;
+use tg_console::log;
+use tg_easy_fs::{FSManager, FileHandle, OpenFlags};
 use tg_kernel_context::{foreign::ForeignContext, LocalContext};
 use tg_kernel_vm::{
-    page_table::{MmuMeta, VAddr, PPN, VPN},
+    page_table::{MmuMeta, VAddr, VPN},
     AddressSpace,
 };
 use tg_signal::Signal;
 use tg_signal_impl::SignalImpl;
+use tg_sync::{Condvar, DeadlockDetector, Mutex as MutexTrait, Semaphore};
 use tg_task_manage::{ProcId, ThreadId};
 use xmas_elf::{
     header::{self, HeaderPt2, Machine},
     program, ElfFile,
 };
-use kernel_elf_parser::{app_stack_region, AuxEntry, AuxType};
 /// 线程
 pub struct Thread {
     /// 不可变
     pub tid: ThreadId,
     /// 可变
     pub context: ForeignContext,
+    /// 线程退出时需要清零的用户地址（`CLONE_CHILD_CLEARTID`/`set_tid_address`）
+    pub clear_child_tid: usize,
+    /// 线程占用的 CPU 时间
+    pub times: CpuTimes,
+    /// 线程阻塞在某个等待队列中，被唤醒时清除
+    pub blocked: bool,
+}
+
+/// CPU 时间，单位为纳秒。
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CpuTimes {
+    /// 用户态时间
+    pub user: usize,
+    /// 内核态时间（处理系统调用的时间）
+    pub system: usize,
+}
+
+impl CpuTimes {
+    /// 用户态与内核态时间之和
+    pub fn total(&self) -> usize {
+        self.user + self.system
+    }
+    /// 累加另一段时间
+    pub fn add(&mut self, other: CpuTimes) {
+        self.user += other.user;
+        self.system += other.system;
+    }
+}
+
+/// 按 Linux `wait` 的约定编码的子进程状态，与 glibc 的 `WIFEXITED` 等宏对应。
+pub mod wait_status {
+    /// 调用 exit 正常结束
+    #[inline]
+    pub const fn exited(code: isize) -> isize {
+        (code & 0xff) << 8
+    }
+    /// 被信号结束
+    #[inline]
+    pub const fn signaled(signo: isize) -> isize {
+        signo & 0x7f
+    }
+    /// 被信号停止
+    #[inline]
+    pub const fn stopped(signo: isize) -> isize {
+        (signo & 0xff) << 8 | 0x7f
+    }
+    /// 收到 SIGCONT 恢复运行
+    pub const CONTINUED: isize = 0xffff;
 }
 
 impl Thread {
@@ -31,6 +87,9 @@ impl Thread {
         Self {
             tid: ThreadId::new(),
             context: ForeignContext { context, satp },
+            clear_child_tid: 0,
+            times: CpuTimes::default(),
+            blocked: false,
         }
     }
 }
@@ -43,46 +102,89 @@ pub struct Process {
     pub address_space: AddressSpace<Sv39, Sv39Manager>,
     /// 文件描述符表
     pub fd_table: Vec<Option<Mutex<Fd>>>,
+    /// 设置了 `FD_CLOEXEC` 的文件描述符，exec 时关闭
+    pub fd_cloexec: BTreeSet<usize>,
     /// 信号模块
     pub signal: Box<dyn Signal>,
     /// 程序堆边界（program break）
     pub heap_start: usize,
     pub heap_end: usize,
+    /// 互斥锁列表
+    pub mutex_list: Vec<Option<Arc<dyn MutexTrait>>>,
+    /// 信号量列表
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    /// 条件变量列表
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    /// 是否开启死锁检测
+    pub deadlock_detect: bool,
+    /// 互斥锁的死锁检测器，资源下标与 `mutex_list` 一致
+    pub mutex_detector: DeadlockDetector,
+    /// 信号量的死锁检测器，资源下标与 `semaphore_list` 一致
+    pub semaphore_detector: DeadlockDetector,
+    /// 所有线程（包括已退出的）占用的 CPU 时间
+    pub times: CpuTimes,
+    /// 已回收的子进程及其后代占用的 CPU 时间
+    pub children_times: CpuTimes,
+    /// 被 SIGSTOP 停止、等待 SIGCONT 的线程
+    pub stopped_threads: Vec<ThreadId>,
 }
 
 impl Process {
-    /// 只支持一个线程
-    pub fn exec(&mut self, elf: ElfFile) {
-        let (proc, thread) = Process::from_elf(elf).unwrap();
+    /// 用新程序替换当前进程，由当前线程执行，其他线程结束
+    ///
+    /// 关闭设置了 `FD_CLOEXEC` 的文件描述符，信号处理函数恢复默认；`elf` 无法加载时返回 [`Process::from_elf`] 的错误码，进程不受影响
+    pub fn exec(&mut self, elf: ElfFile, execfn: &str, args: &[String], envs: &[String]) -> Result<(), isize> {
+        let (proc, thread) = Process::from_elf(elf, execfn, args, envs)?;
+        let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
+        unsafe { (*processor).make_others_exited() };
+        self.stopped_threads.clear();
+        for fd in core::mem::take(&mut self.fd_cloexec) {
+            if let Some(slot) = self.fd_table.get_mut(fd) {
+                slot.take();
+            }
+        }
+        self.signal.clear();
+        self.sync_mappings();
         self.address_space = proc.address_space;
         self.heap_start = proc.heap_start;
         self.heap_end = proc.heap_end;
-        let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
-        unsafe {
-            let pthreads = (*processor).get_thread(self.pid).unwrap();
-            (*processor).get_task(pthreads[0]).unwrap().context = thread.context;
+        self.mutex_list = proc.mutex_list;
+        self.semaphore_list = proc.semaphore_list;
+        self.condvar_list = proc.condvar_list;
+        self.deadlock_detect = proc.deadlock_detect;
+        self.mutex_detector = proc.mutex_detector;
+        self.semaphore_detector = proc.semaphore_detector;
+        let current = unsafe { (*processor).current().unwrap() };
+        current.context = thread.context;
+        // 旧地址空间已不存在，不再需要在退出时清零 tid
+        current.clear_child_tid = 0;
+        Ok(())
+    }
+    /// 把共享文件映射的修改写回文件，进程退出和 exec 时调用
+    pub fn sync_mappings(&self) {
+        for area in &self.address_space.areas {
+            self.address_space.sync(area.clone());
         }
     }
-    /// 只支持一个线程
-    pub fn fork(&mut self) -> Option<(Self, Thread)> {
-        // 子进程 pid
-        let pid = ProcId::new();
+    /// 在当前进程中创建一个新线程，与其他线程共享地址空间、文件描述符表和信号模块
+    pub fn new_thread(&self, context: LocalContext) -> Thread {
+        let satp = (8 << 60) | self.address_space.root_ppn().val();
+        Thread::new(satp, context)
+    }
+    /// 复制当前进程，子进程只包含一个线程，其上下文为 `context`
+    ///
+    /// 物理页不足以复制地址空间时返回 `None`，已经复制的部分随之释放
+    pub fn fork(&mut self, context: LocalContext) -> Option<(Self, Thread)> {
         // 复制父进程地址空间
-        let parent_addr_space = &self.address_space;
-        let mut address_space: AddressSpace<Sv39, Sv39Manager> = AddressSpace::new();
-        parent_addr_space.cloneself(&mut address_space);
+        let parent_addr_space = &mut self.address_space;
+        let mut address_space: AddressSpace<Sv39, Sv39Manager> = AddressSpace::new()?;
+        if !parent_addr_space.cloneself(&mut address_space) {
+            return None;
+        }
         map_portal(&address_space);
+        // 子进程 pid
+        let pid = ProcId::new();
         // 线程
-        let processor: *mut ProcessorInner = PROCESSOR.get_mut() as *mut ProcessorInner;
-        let pthreads = unsafe { (*processor).get_thread(self.pid).unwrap() };
-        let context = unsafe {
-            (*processor)
-                .get_task(pthreads[0])
-                .unwrap()
-                .context
-                .context
-                .clone()
-        };
         let satp = (8 << 60) | address_space.root_ppn().val();
         let thread = Thread::new(satp, context);
         // 复制父进程文件符描述表
@@ -96,76 +198,48 @@ impl Process {
                 pid,
                 address_space,
                 fd_table: new_fd_table,
+                fd_cloexec: self.fd_cloexec.clone(),
                 signal: self.signal.from_fork(),
                 heap_start: self.heap_start,
                 heap_end: self.heap_end,
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                deadlock_detect: false,
+                mutex_detector: DeadlockDetector::new(),
+                semaphore_detector: DeadlockDetector::new(),
+                times: CpuTimes::default(),
+                children_times: CpuTimes::default(),
+                stopped_threads: Vec::new(),
             },
             thread,
         ))
     }
 
-    pub fn from_elf(elf: ElfFile) -> Option<(Self, Thread)> {
-        let entry = match elf.header.pt2 {
-            HeaderPt2::Header64(pt2)
-                if pt2.type_.as_type() == header::Type::Executable
-                    && pt2.machine.as_machine() == Machine::RISC_V =>
-            {
-                pt2.entry_point as usize
+    /// 从 ELF 创建进程，用户栈上放入参数 `args`、环境变量 `envs` 和 auxv，`execfn` 为程序路径
+    ///
+    /// 程序或解释器格式不对时返回 -ENOEXEC，找不到 `PT_INTERP` 指定的解释器时返回 -ENOENT，物理页不足时返回 -ENOMEM
+    pub fn from_elf(
+        elf: ElfFile,
+        execfn: &str,
+        args: &[String],
+        envs: &[String],
+    ) -> Result<(Self, Thread), isize> {
+        let mut address_space = AddressSpace::new().ok_or(-12isize)?; // -ENOMEM
+        let main = load_elf(&mut address_space, &elf, PIE_BASE)?;
+        // 动态链接的程序先由解释器（动态链接器）运行，解释器再加载共享库并跳到程序入口
+        let interp = match interp_path(&elf).ok_or(-8isize)? { // -ENOEXEC
+            Some(path) => {
+                log::info!("from_elf: PT_INTERP {}", path);
+                let data = read_all(open_interp(path).ok_or(-2isize)?); // -ENOENT
+                let interp_elf = ElfFile::new(&data).map_err(|_| -8isize)?; // -ENOEXEC
+                Some(load_elf(&mut address_space, &interp_elf, INTERP_BASE)?)
             }
-            _ => None?,
+            None => None,
         };
+        let max_end_mem = main.end;
+        let entry = interp.as_ref().map_or(main.entry, |interp| interp.entry);
 
-        const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
-        const PAGE_MASK: usize = PAGE_SIZE - 1;
-
-        let mut address_space = AddressSpace::new();
-        let mut max_end_mem = 0usize;  // 跟踪 ELF 加载的最高地址
-        
-        // 输出日志
-        extern crate tg_console;
-        use tg_console::log;
-        log::info!("from_elf: Loading ELF, entry={:#x}", entry);
-        
-        for program in elf.program_iter() {
-            let prog_type = program.get_type();
-            log::info!("from_elf: Program header type={:?}", prog_type);
-            
-            if !matches!(prog_type, Ok(program::Type::Load)) {
-                continue;
-            }
-
-            let off_file = program.offset() as usize;
-            let len_file = program.file_size() as usize;
-            let off_mem = program.virtual_addr() as usize;
-            let end_mem = off_mem + program.mem_size() as usize;
-            assert_eq!(off_file & PAGE_MASK, off_mem & PAGE_MASK);
-
-            log::info!("from_elf: LOAD segment vaddr={:#x}, memsz={:#x}, end={:#x}", 
-                       off_mem, program.mem_size(), end_mem);
-            
-            // 更新最高地址
-            if end_mem > max_end_mem {
-                max_end_mem = end_mem;
-            }
-
-            let mut flags: [u8; 5] = *b"U___V";
-            if program.flags().is_execute() {
-                flags[1] = b'X';
-            }
-            if program.flags().is_write() {
-                flags[2] = b'W';
-            }
-            if program.flags().is_read() {
-                flags[3] = b'R';
-            }
-            address_space.map(
-                VAddr::new(off_mem).floor()..VAddr::new(end_mem).ceil(),
-                &elf.input[off_file..][..len_file],
-                off_mem & PAGE_MASK,
-                parse_flags(unsafe { core::str::from_utf8_unchecked(&flags) }).unwrap(),
-            );
-        }
-        
         // 设置堆起始地址：ELF 加载的最高地址之后的下一页，确保页对齐
         const PAGE_SIZE_FOR_HEAP: usize = 1 << Sv39::PAGE_BITS;
         let heap_start = if max_end_mem % PAGE_SIZE_FOR_HEAP == 0 {
@@ -175,74 +249,47 @@ impl Process {
         };
         log::info!("from_elf: max_end_mem={:#x}, heap_start={:#x}", max_end_mem, heap_start);
         // 映射用户栈 - 增加栈大小以支持 Linux 程序
-        // 用 128 个页面 (512KB) 而不是原来的 2 个页面 (8KB)
+        // 用 128 个页面 (512KB) 而不是原来的 2 个页面 (8KB)，物理页在首次访问时分配
         // 注意：我们映射到包括 0x4000000000 的那一页，以便 glibc 可以访问栈顶地址
-        const STACK_PAGES: usize = 128;
-        let stack = unsafe {
-            alloc_zeroed(Layout::from_size_align_unchecked(
-                (STACK_PAGES + 1) << Sv39::PAGE_BITS,  // 多分配一页
-                1 << Sv39::PAGE_BITS,
-            ))
-        };
         // 调整映射范围：从 stack_bottom 映射到 stack_top+1 页
         // 栈顶地址设为 0x4000000000 所在的那一页也被映射
-        let stack_top_vpn = VPN::new((1 << 26) + 1);  // 映射到 0x4000001000
-        let stack_bottom_vpn = VPN::new(((1 << 26) + 1) - (STACK_PAGES + 1));
-        address_space.map_extern(
-            stack_bottom_vpn..stack_top_vpn,
-            PPN::new(stack as usize >> Sv39::PAGE_BITS),
-            build_flags("U_WRV"),
-        );
+        let stack_top_vpn = VPN::new(USER_TOP_VPN + 1);  // 映射到 0x4000001000
+        let stack_bottom_vpn = VPN::new(USER_TOP_VPN - USER_STACK_PAGES);
+        address_space.map_lazy(stack_bottom_vpn..stack_top_vpn, build_flags("U_WRV"));
         // 映射异界传送门
         map_portal(&address_space);
         let satp = (8 << 60) | address_space.root_ppn().val();
         let mut context = LocalContext::user(entry);
         
-        // 使用 kernel-elf-parser 生成栈数据
-        let stack_bottom_vaddr = ((1usize << 26) - STACK_PAGES) << Sv39::PAGE_BITS;
-        let stack_top_vaddr = (1usize << 26) << Sv39::PAGE_BITS;  // 0x4000000000
-        let stack_phys = stack as *mut u8;
-        
-        // 手动构建 auxiliary vector
-        let page_size = 1 << Sv39::PAGE_BITS;  // 4096
-        let phdr = 0x10040;  // Program headers 的虚拟地址（ELF 中的位置）
-        let phent = elf.header.pt2.ph_entry_size() as usize;
-        let phnum = elf.header.pt2.ph_count() as usize;
-        
-        let auxv = vec![
-            AuxEntry::new(AuxType::PHDR, phdr),
-            AuxEntry::new(AuxType::PHENT, phent),
-            AuxEntry::new(AuxType::PHNUM, phnum),
-            AuxEntry::new(AuxType::PAGESZ, page_size),
-            AuxEntry::new(AuxType::ENTRY, entry),
+        let stack_bottom_vaddr = (USER_TOP_VPN - USER_STACK_PAGES) << Sv39::PAGE_BITS;
+        let stack_top_vaddr = USER_TOP_VPN << Sv39::PAGE_BITS;  // 0x4000000000
+
+        let auxv = [
+            (AT_PHDR, main.phdr),
+            (AT_PHENT, elf.header.pt2.ph_entry_size() as usize),
+            (AT_PHNUM, elf.header.pt2.ph_count() as usize),
+            (AT_PAGESZ, 1 << Sv39::PAGE_BITS),
+            // 解释器的加载地址，没有解释器时为 0
+            (AT_BASE, interp.as_ref().map_or(0, |interp| interp.bias)),
+            (AT_ENTRY, main.entry),
+            (AT_UID, 0),
+            (AT_EUID, 0),
+            (AT_GID, 0),
+            (AT_EGID, 0),
+            (AT_HWCAP, user_stack::HWCAP),
+            (AT_CLKTCK, tg_syscall::Tms::CLK_TCK),
+            (AT_SECURE, 0),
         ];
-        
-        // 准备参数和环境变量（简化版：只传入程序名）
-        let args = vec![String::from("app")];
-        let envs: Vec<String> = vec![];
-        
-        // 使用 app_stack_region 生成栈数据
-        let stack_data = app_stack_region(&args, &envs, &auxv, stack_top_vaddr);
-        
-        // 计算用户栈指针位置
-        let user_sp = stack_top_vaddr - stack_data.len();
-        
-        // 将栈数据写入物理内存
-        let sp_offset = user_sp - stack_bottom_vaddr;
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                stack_data.as_ptr(),
-                stack_phys.add(sp_offset),
-                stack_data.len()
-            );
-        }
-        
+        let user_sp = UserStack::new(&mut address_space, stack_bottom_vaddr, stack_top_vaddr)
+            .init(execfn, args, envs, &auxv)
+            .ok_or(-12isize)?; // -ENOMEM
+
         // 设置栈指针
         *context.sp_mut() = user_sp;
         
         let thread = Thread::new(satp, context);
 
-        Some((
+        Ok((
             Self {
                 pid: ProcId::new(),
                 address_space,
@@ -263,11 +310,174 @@ impl Process {
                         write: true,
                     })),
                 ],
+                fd_cloexec: BTreeSet::new(),
                 signal: Box::new(SignalImpl::new()),
                 heap_start,
                 heap_end: heap_start,  // 初始时堆为空
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                deadlock_detect: false,
+                mutex_detector: DeadlockDetector::new(),
+                semaphore_detector: DeadlockDetector::new(),
+                times: CpuTimes::default(),
+                children_times: CpuTimes::default(),
+                stopped_threads: Vec::new(),
             },
             thread,
         ))
     }
 }
+
+/// 用户地址空间的上界（页号），其上只有主线程栈顶的一页
+pub const USER_TOP_VPN: usize = 1 << 26;
+/// 主线程栈的页数，栈位于 [`USER_TOP_VPN`] 之下
+pub const USER_STACK_PAGES: usize = 128;
+
+/// ET_DYN 程序（PIE）的加载地址
+const PIE_BASE: usize = 0x1_0000_0000;
+/// 动态链接器的加载地址，位于程序和堆之上、用户栈之下
+const INTERP_BASE: usize = 0x20_0000_0000;
+
+/// 装入地址空间的 ELF 映像。
+struct LoadedElf {
+    /// 加载偏移，ET_EXEC 为 0
+    bias: usize,
+    /// 入口地址
+    entry: usize,
+    /// 程序头表的地址，找不到时为 0
+    phdr: usize,
+    /// 各段结束地址的最大值
+    end: usize,
+}
+
+/// 检查 ELF 头并把 LOAD 段映射到 `address_space`，ET_DYN 映像加载到 `dyn_base`。
+///
+/// 格式不对时返回 -ENOEXEC，物理页不足时返回 -ENOMEM。
+fn load_elf(
+    address_space: &mut AddressSpace<Sv39, Sv39Manager>,
+    elf: &ElfFile,
+    dyn_base: usize,
+) -> Result<LoadedElf, isize> {
+    const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+    const PAGE_MASK: usize = PAGE_SIZE - 1;
+
+    let HeaderPt2::Header64(pt2) = elf.header.pt2 else {
+        return Err(-8); // -ENOEXEC
+    };
+    if pt2.machine.as_machine() != Machine::RISC_V {
+        return Err(-8); // -ENOEXEC
+    }
+    let loads = || {
+        elf.program_iter()
+            .filter(|program| matches!(program.get_type(), Ok(program::Type::Load)))
+    };
+    let bias = match pt2.type_.as_type() {
+        header::Type::Executable => 0,
+        // 位置无关的映像整体平移，使最低的段从 dyn_base 开始
+        header::Type::SharedObject => {
+            let min_vaddr = loads().map(|program| program.virtual_addr() as usize).min().ok_or(-8isize)?; // -ENOEXEC
+            dyn_base.checked_sub(min_vaddr & !PAGE_MASK).ok_or(-8isize)? // -ENOEXEC
+        }
+        _ => return Err(-8), // -ENOEXEC
+    };
+    let entry = (pt2.entry_point as usize).checked_add(bias).ok_or(-8isize)?; // -ENOEXEC
+    log::info!("from_elf: Loading ELF, entry={:#x}, bias={:#x}", entry, bias);
+
+    // 段须位于用户栈之下
+    const LOAD_TOP: usize = (USER_TOP_VPN - USER_STACK_PAGES) << Sv39::PAGE_BITS;
+    let mut end = 0usize;
+    for program in loads() {
+        let off_file = program.offset() as usize;
+        let len_file = program.file_size() as usize;
+        let len_mem = program.mem_size() as usize;
+        let (Some(off_mem), Some(end_file)) = (
+            (program.virtual_addr() as usize).checked_add(bias),
+            off_file.checked_add(len_file),
+        ) else {
+            return Err(-8); // -ENOEXEC
+        };
+        let Some(end_mem) = off_mem.checked_add(len_mem).filter(|&end_mem| end_mem <= LOAD_TOP) else {
+            return Err(-8); // -ENOEXEC
+        };
+        // 段在文件中的偏移须与虚拟地址同余，且不超出文件和内存中的大小
+        if off_file & PAGE_MASK != off_mem & PAGE_MASK || end_file > elf.input.len() || len_file > len_mem {
+            return Err(-8); // -ENOEXEC
+        }
+        // 不能与已经映射的段（或加载解释器时的程序）重叠
+        let range = VAddr::<Sv39>::new(off_mem).floor()..VAddr::<Sv39>::new(end_mem).ceil();
+        if address_space
+            .areas
+            .iter()
+            .any(|area| area.start < range.end && range.start < area.end)
+        {
+            return Err(-8); // -ENOEXEC
+        }
+        log::info!("from_elf: LOAD segment vaddr={:#x}, memsz={:#x}, end={:#x}",
+                   off_mem, program.mem_size(), end_mem);
+        end = end.max(end_mem);
+
+        let mut flags: [u8; 5] = *b"U___V";
+        if program.flags().is_execute() {
+            flags[1] = b'X';
+        }
+        if program.flags().is_write() {
+            flags[2] = b'W';
+        }
+        if program.flags().is_read() {
+            flags[3] = b'R';
+        }
+        if !address_space.map(
+            range,
+            &elf.input[off_file..][..len_file],
+            off_mem & PAGE_MASK,
+            parse_flags(unsafe { core::str::from_utf8_unchecked(&flags) }).unwrap(),
+        ) {
+            return Err(-12); // -ENOMEM
+        }
+    }
+
+    // 程序头表的地址：优先取 PT_PHDR，否则由包含 e_phoff 的 LOAD 段换算
+    let ph_offset = pt2.ph_offset as usize;
+    let phdr = elf
+        .program_iter()
+        .find(|program| matches!(program.get_type(), Ok(program::Type::Phdr)))
+        .and_then(|program| (program.virtual_addr() as usize).checked_add(bias))
+        .or_else(|| {
+            loads().find_map(|program| {
+                let offset = program.offset() as usize;
+                (offset..offset + program.file_size() as usize)
+                    .contains(&ph_offset)
+                    .then(|| program.virtual_addr() as usize + bias + (ph_offset - offset))
+            })
+        })
+        .unwrap_or(0);
+    Ok(LoadedElf {
+        bias,
+        entry,
+        phdr,
+        end,
+    })
+}
+
+/// `PT_INTERP` 指定的解释器路径，没有时返回 `Some(None)`，路径无效时返回 `None`。
+fn interp_path<'a>(elf: &ElfFile<'a>) -> Option<Option<&'a str>> {
+    let Some(program) = elf
+        .program_iter()
+        .find(|program| matches!(program.get_type(), Ok(program::Type::Interp)))
+    else {
+        return Some(None);
+    };
+    let path = elf
+        .input
+        .get(program.offset() as usize..)?
+        .get(..program.file_size() as usize)?;
+    let path = core::str::from_utf8(path).ok()?.trim_end_matches('\0');
+    Some(Some(path))
+}
+
+/// 打开解释器。easy-fs 只有根目录，找不到完整路径时按文件名查找。
+pub fn open_interp(path: &str) -> Option<Arc<FileHandle>> {
+    FS.open(path, OpenFlags::RDONLY)
+        .or_else(|| FS.open(path.rsplit('/').next()?, OpenFlags::RDONLY))
+}
@@ -1,7 +1,13 @@
-use crate::process::{Process, Thread};
-use alloc::collections::{BTreeMap, VecDeque};
+use crate::{
+    frame,
+    process::{CpuTimes, Process, Thread},
+    timer,
+};
+use alloc::{collections::BTreeMap, vec::Vec};
 use core::cell::UnsafeCell;
-use tg_task_manage::{Manage, PThreadManager, ProcId, Schedule, ThreadId};
+use spin::Mutex;
+use tg_console::log;
+use tg_task_manage::{ClassScheduler, Manage, PThreadManager, ProcId, Schedule, ThreadId};
 
 pub type ProcessorInner = PThreadManager<Process, Thread, ThreadManager, ProcManager>;
 
@@ -27,12 +33,65 @@ impl Processor {
 /// 处理器实例
 pub static PROCESSOR: Processor = Processor::new();
 
+/// 阻塞当前线程，它之后只能由 [`wake_thread`] 唤醒。
+pub fn block_current(processor: &mut ProcessorInner) {
+    processor.current().unwrap().blocked = true;
+    processor.make_current_blocked();
+}
+
+/// 唤醒阻塞的线程，取消它的超时并重新加入调度队列。
+///
+/// 线程已经不存在（例如所在进程已退出）或没有阻塞（已经被唤醒过）时返回 false，不会重复入队。
+pub fn wake_thread(tid: ThreadId) -> bool {
+    let processor = PROCESSOR.get_mut();
+    match processor.get_task(tid) {
+        Some(thread) if thread.blocked => {
+            thread.blocked = false;
+            timer::cancel_timeout(tid);
+            processor.re_enque(tid);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 改写阻塞线程被唤醒后得到的系统调用返回值，trap 循环阻塞线程时已将其设为 0。
+pub fn set_blocked_ret(tid: ThreadId, ret: isize) {
+    if let Some(thread) = PROCESSOR.get_mut().get_task(tid) {
+        *thread.context.context.a_mut(0) = ret as _;
+    }
+}
+
+/// 阻塞在 wait4 中、等待子进程状态变化的线程
+static CHILD_WAITERS: Mutex<Vec<ThreadId>> = Mutex::new(Vec::new());
+
+/// 线程 `tid` 在 wait4 中阻塞，直到某个子进程退出、停止或恢复运行。
+pub fn wait_child_event(tid: ThreadId) {
+    CHILD_WAITERS.lock().push(tid);
+}
+
+/// 线程 `tid` 不再等待子进程状态变化，它不在 wait4 中阻塞时返回 false。
+pub fn cancel_child_wait(tid: ThreadId) -> bool {
+    let mut waiters = CHILD_WAITERS.lock();
+    let len = waiters.len();
+    waiters.retain(|&waiter| waiter != tid);
+    waiters.len() != len
+}
+
+/// 有子进程状态变化，唤醒所有阻塞在 wait4 中的线程重新检查。
+pub fn notify_child_event() {
+    let waiters = core::mem::take(&mut *CHILD_WAITERS.lock());
+    for tid in waiters {
+        wake_thread(tid);
+    }
+}
+
 /// 任务管理器
 /// `tasks` 中保存所有的任务实体
-/// `ready_queue` 删除任务的实体
+/// `scheduler` 按每个线程的调度策略管理就绪队列
 pub struct ThreadManager {
     tasks: BTreeMap<ThreadId, Thread>,
-    ready_queue: VecDeque<ThreadId>,
+    scheduler: ClassScheduler<ThreadId>,
 }
 
 impl ThreadManager {
@@ -40,9 +99,15 @@ impl ThreadManager {
     pub fn new() -> Self {
         Self {
             tasks: BTreeMap::new(),
-            ready_queue: VecDeque::new(),
+            scheduler: ClassScheduler::new(),
         }
     }
+
+    /// 调度器，用于设置线程的调度策略
+    #[inline]
+    pub fn scheduler(&mut self) -> &mut ClassScheduler<ThreadId> {
+        &mut self.scheduler
+    }
 }
 
 impl Manage<Thread, ThreadId> for ThreadManager {
@@ -60,24 +125,31 @@ impl Manage<Thread, ThreadId> for ThreadManager {
     #[inline]
     fn delete(&mut self, id: ThreadId) {
         self.tasks.remove(&id);
+        self.scheduler.remove(id);
     }
 }
 
 impl Schedule<ThreadId> for ThreadManager {
     /// 添加 id 进入调度队列
     fn add(&mut self, id: ThreadId) {
-        self.ready_queue.push_back(id);
+        self.scheduler.add(id);
     }
     /// 从调度队列中取出 id
     fn fetch(&mut self) -> Option<ThreadId> {
-        self.ready_queue.pop_front()
+        self.scheduler.fetch()
+    }
+    /// 时间片用完的 id 重新进入调度队列
+    fn add_preempted(&mut self, id: ThreadId) {
+        self.scheduler.add_preempted(id);
     }
 }
 
 /// 进程管理器
 /// `procs` 中保存所有的进程实体
+/// `exited` 保存已退出、尚未被回收的进程占用的 CPU 时间
 pub struct ProcManager {
     procs: BTreeMap<ProcId, Process>,
+    exited: BTreeMap<ProcId, CpuTimes>,
 }
 
 impl ProcManager {
@@ -85,8 +157,19 @@ impl ProcManager {
     pub fn new() -> Self {
         Self {
             procs: BTreeMap::new(),
+            exited: BTreeMap::new(),
         }
     }
+
+    /// 所有进程的 id，从小到大排列
+    pub fn pids(&self) -> Vec<ProcId> {
+        self.procs.keys().copied().collect()
+    }
+
+    /// 回收子进程时取出它（包括其已回收的后代）占用的 CPU 时间
+    pub fn take_exited_times(&mut self, id: ProcId) -> CpuTimes {
+        self.exited.remove(&id).unwrap_or_default()
+    }
 }
 
 impl Manage<Process, ProcId> for ProcManager {
@@ -103,6 +186,15 @@ impl Manage<Process, ProcId> for ProcManager {
     /// 删除任务实体
     #[inline]
     fn delete(&mut self, id: ProcId) {
-        self.procs.remove(&id);
+        if let Some(proc) = self.procs.remove(&id) {
+            proc.sync_mappings();
+            let mut times = proc.times;
+            times.add(proc.children_times);
+            self.exited.insert(id, times);
+            notify_child_event();
+            // 地址空间随进程实体释放，物理页帧归还分配器
+            drop(proc);
+            log::debug!("process {} exited, {}", id.get_usize(), frame::stats());
+        }
     }
 }
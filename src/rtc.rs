@@ -0,0 +1,29 @@
+//! Goldfish RTC 驱动。
+//!
+//! QEMU virt 机器的实时时钟，寄存器见
+//! <https://android.googlesource.com/platform/external/qemu/+/master/docs/GOLDFISH-VIRTUAL-HARDWARE.TXT>。
+
+/// 读取时先读低 32 位，同时锁存高 32 位
+const TIME_LOW: usize = 0x00;
+const TIME_HIGH: usize = 0x04;
+
+/// Goldfish RTC 设备。
+pub struct GoldfishRtc {
+    base: usize,
+}
+
+impl GoldfishRtc {
+    /// `base` 为设备的 MMIO 基址，需要在当前地址空间中可以访问。
+    pub const fn new(base: usize) -> Self {
+        Self { base }
+    }
+
+    /// 自 1970-01-01 00:00:00 UTC 以来的纳秒数。
+    pub fn read_ns(&self) -> usize {
+        unsafe {
+            let low = core::ptr::read_volatile((self.base + TIME_LOW) as *const u32);
+            let high = core::ptr::read_volatile((self.base + TIME_HIGH) as *const u32);
+            (high as usize) << 32 | low as usize
+        }
+    }
+}
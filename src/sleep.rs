@@ -0,0 +1,62 @@
+//! 睡眠队列。
+//!
+//! 睡眠的线程按到期时刻排在 `timer` 的超时队列中，到期后由调度循环唤醒（时钟中断或空转等待之后）。
+//! 这里另外记录剩余时间写回的位置，线程被信号打断时写回 `rem` 并返回 -EINTR。
+//! `rem` 记录为用户虚拟地址，写回时重新翻译：睡眠期间页可能被换出、取消映射或写时复制。
+
+use crate::{
+    build_flags,
+    processor::{set_blocked_ret, wake_thread},
+    timer, Sv39, PROCESSOR,
+};
+use alloc::collections::BTreeMap;
+use spin::Mutex;
+use tg_kernel_vm::page_table::{VAddr, VmFlags};
+use tg_syscall::TimeSpec;
+use tg_task_manage::{ProcId, ThreadId};
+
+/// 睡眠中的线程。
+struct Sleeper {
+    /// 线程所属的进程
+    pid: ProcId,
+    deadline: usize,
+    /// 用户 `rem` 的虚拟地址，0 表示不需要写回
+    rem: usize,
+}
+
+static SLEEPERS: Mutex<BTreeMap<ThreadId, Sleeper>> = Mutex::new(BTreeMap::new());
+
+/// 让进程 `pid` 中的线程 `tid` 睡眠到单调时钟的 `deadline`，实际的阻塞由 trap 循环完成。
+pub fn sleep(pid: ProcId, tid: ThreadId, deadline: usize, rem: usize) {
+    SLEEPERS.lock().insert(tid, Sleeper { pid, deadline, rem });
+    timer::set_timeout(tid, deadline, move || {
+        SLEEPERS.lock().remove(&tid);
+        wake_thread(tid);
+    });
+}
+
+/// 打断线程的睡眠，写回剩余时间并使其返回 -EINTR。
+///
+/// 线程不在睡眠时返回 false。
+pub fn interrupt(tid: ThreadId) -> bool {
+    let Some(sleeper) = SLEEPERS.lock().remove(&tid) else {
+        return false;
+    };
+    // 进程已经不存在或 `rem` 已不可写时不写回
+    const WRITEABLE: VmFlags<Sv39> = build_flags("W_V");
+    let proc = PROCESSOR.get_mut().get_proc(sleeper.pid);
+    if let Some(proc) = proc.filter(|_| sleeper.rem != 0) {
+        if let Some(mut ptr) = proc
+            .address_space
+            .translate_mut::<TimeSpec>(VAddr::new(sleeper.rem), WRITEABLE)
+        {
+            let left = sleeper.deadline.saturating_sub(timer::now_ns());
+            *unsafe { ptr.as_mut() } = TimeSpec {
+                tv_sec: left / 1_000_000_000,
+                tv_nsec: left % 1_000_000_000,
+            };
+        }
+    }
+    set_blocked_ret(tid, -4); // -EINTR
+    wake_thread(tid)
+}
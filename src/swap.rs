@@ -0,0 +1,127 @@
+//! 交换区。
+//!
+//! 第二块 virtio-blk 磁盘（QEMU 的 `virtio-mmio-bus.1`）作为交换设备，按页划分为槽位，空闲槽位记录在位图中。
+//! 每次从用户态 trap 进内核时，空闲页帧少于 [`LOW_WATERMARK`] 就调用 [`reclaim`]，依次让各进程的地址空间按 CLOCK 算法换出冷页；
+//! 分配页帧仍然失败时，分配路径调用 [`reclaim_for_alloc`] 从当前进程以外的进程换出后重试一次。
+//! 换出的页表项清除有效位、保留页属性，页号字段保存槽位号；缺页时 `populate` 通过 `Sv39Manager::swap_in` 读回。
+//! 换入的页帧保留原来的槽位，没有被写过（脏位为 0）时再次换出不需要写盘。
+
+use crate::{frame, processor::ProcessorInner, virtio_block::SWAP_DEVICE, Sv39};
+use alloc::{sync::Arc, vec, vec::Vec};
+use spin::{Lazy, Mutex};
+use tg_console::log;
+use tg_easy_fs::{BlockDevice, BLOCK_SZ};
+use tg_kernel_vm::page_table::MmuMeta;
+use tg_task_manage::{Manage, ProcId};
+
+/// 交换区大小，与构建脚本生成的 `swap.img` 一致
+pub const SWAP_SIZE: usize = 32 << 20;
+/// 空闲页帧少于此数时换出
+const LOW_WATERMARK: usize = 64;
+/// 每次换出的页数
+const RECLAIM_BATCH: usize = 128;
+
+const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+/// 一页占用的块数
+const BLOCKS_PER_PAGE: usize = PAGE_SIZE / BLOCK_SZ;
+
+struct SwapSpace {
+    device: Arc<dyn BlockDevice>,
+    /// 置位表示槽位已使用
+    bitmap: Vec<u64>,
+    /// 下次从这个槽位开始查找
+    next: usize,
+}
+
+static SWAP: Lazy<Option<Mutex<SwapSpace>>> = Lazy::new(|| {
+    let device = SWAP_DEVICE.clone()?;
+    let slots = SWAP_SIZE / PAGE_SIZE;
+    log::info!("(swap) -> {slots} slots");
+    Some(Mutex::new(SwapSpace {
+        device,
+        bitmap: vec![0; slots.div_ceil(64)],
+        next: 0,
+    }))
+});
+
+/// 下一个换出页的进程，各进程轮流换出
+static NEXT_PROC: Mutex<usize> = Mutex::new(0);
+
+/// 分配一个空闲槽位，交换区已满或没有交换设备时返回 `None`。
+pub fn alloc_slot() -> Option<usize> {
+    let mut swap = SWAP.as_ref()?.lock();
+    let slots = swap.bitmap.len() * 64;
+    let slot = (swap.next..slots)
+        .chain(0..swap.next)
+        .find(|&slot| swap.bitmap[slot / 64] & (1 << (slot % 64)) == 0)?;
+    swap.bitmap[slot / 64] |= 1 << (slot % 64);
+    swap.next = slot + 1;
+    Some(slot)
+}
+
+/// 释放槽位 `slot`。
+pub fn free_slot(slot: usize) {
+    if let Some(swap) = SWAP.as_ref() {
+        swap.lock().bitmap[slot / 64] &= !(1 << (slot % 64));
+    }
+}
+
+/// 把一页内容写入槽位 `slot`。
+pub fn write_page(slot: usize, page: &[u8]) {
+    let swap = SWAP.as_ref().unwrap().lock();
+    for (i, block) in page.chunks(BLOCK_SZ).enumerate() {
+        swap.device.write_block(slot * BLOCKS_PER_PAGE + i, block);
+    }
+}
+
+/// 从槽位 `slot` 读出一页内容。
+pub fn read_page(slot: usize, page: &mut [u8]) {
+    let swap = SWAP.as_ref().unwrap().lock();
+    for (i, block) in page.chunks_mut(BLOCK_SZ).enumerate() {
+        swap.device.read_block(slot * BLOCKS_PER_PAGE + i, block);
+    }
+}
+
+/// 空闲页帧不足时，从各进程轮流换出冷页，直到换出 [`RECLAIM_BATCH`] 页或没有页可换出。
+///
+/// 只在 trap 进入内核时调用，此时内核没有持有任何用户页的引用。
+pub fn reclaim(processor: &mut ProcessorInner) {
+    if SWAP.is_none() || frame::stats().free >= LOW_WATERMARK {
+        return;
+    }
+    evict(processor, None);
+}
+
+/// 分配页帧失败时调用，不论空闲页帧多少都换出一批冷页。
+///
+/// 当前进程的地址空间可能正被修改（缺页处理、fork 复制、exec 加载），不从中换出；还没有当前进程时什么也不做。
+pub fn reclaim_for_alloc(processor: &mut ProcessorInner) {
+    if SWAP.is_none() {
+        return;
+    }
+    let Some(current) = processor.get_current_proc().map(|proc| proc.pid) else {
+        return;
+    };
+    evict(processor, Some(current));
+}
+
+/// 从 `skip` 以外的进程轮流换出冷页。
+fn evict(processor: &mut ProcessorInner, skip: Option<ProcId>) {
+    let manager = processor.proc_manager();
+    let pids = manager.pids();
+    let mut next = NEXT_PROC.lock();
+    let start = pids.iter().position(|pid| pid.get_usize() >= *next).unwrap_or(0);
+    let mut evicted = 0;
+    for &pid in pids[start..].iter().chain(&pids[..start]) {
+        if Some(pid) == skip {
+            continue;
+        }
+        let proc = manager.get_mut(pid).unwrap();
+        evicted += proc.address_space.reclaim(RECLAIM_BATCH - evicted);
+        *next = pid.get_usize() + 1;
+        if evicted == RECLAIM_BATCH {
+            break;
+        }
+    }
+    log::debug!("swap out {evicted} pages, {}", frame::stats());
+}
@@ -0,0 +1,144 @@
+//! 时钟与超时。
+
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::Mutex;
+use tg_task_manage::ThreadId;
+
+/// `time` 寄存器每秒的计数，QEMU virt 机器为 10MHz，启动时从设备树读取。
+static TIMEBASE_FREQ: AtomicUsize = AtomicUsize::new(10_000_000);
+
+/// 实时时钟与单调时钟之差，单位为纳秒。
+static REALTIME_OFFSET: AtomicUsize = AtomicUsize::new(0);
+
+const NS_PER_SEC: usize = 1_000_000_000;
+
+/// 设置 `time` 寄存器的频率，`None` 时保持默认值。
+pub fn init_timebase(freq: Option<usize>) {
+    if let Some(freq) = freq.filter(|&freq| freq > 0) {
+        TIMEBASE_FREQ.store(freq, Ordering::Relaxed);
+    }
+}
+
+/// 用启动时从 RTC 读到的墙上时间校准实时时钟。
+pub fn init_realtime(realtime_ns: usize) {
+    REALTIME_OFFSET.store(realtime_ns.saturating_sub(now_ns()), Ordering::Relaxed);
+}
+
+/// 当前单调时钟，单位为纳秒。
+#[inline]
+pub fn now_ns() -> usize {
+    let ticks = riscv::register::time::read();
+    let freq = TIMEBASE_FREQ.load(Ordering::Relaxed);
+    // 分开计算整秒和余数，避免乘法溢出
+    ticks / freq * NS_PER_SEC + ticks % freq * NS_PER_SEC / freq
+}
+
+/// 当前实时时钟（自 1970-01-01 00:00:00 UTC），单位为纳秒。
+#[inline]
+pub fn realtime_ns() -> usize {
+    now_ns() + REALTIME_OFFSET.load(Ordering::Relaxed)
+}
+
+/// 实时时钟的时刻转换为单调时钟的时刻。
+#[inline]
+pub fn realtime_to_monotonic(ns: usize) -> usize {
+    ns.saturating_sub(REALTIME_OFFSET.load(Ordering::Relaxed))
+}
+
+/// 时钟精度，即 `time` 寄存器一次计数的纳秒数。
+pub fn resolution_ns() -> usize {
+    NS_PER_SEC.div_ceil(TIMEBASE_FREQ.load(Ordering::Relaxed))
+}
+
+/// 纳秒转换为 `time` 寄存器的计数。
+#[inline]
+fn ns_to_ticks(ns: usize) -> usize {
+    let freq = TIMEBASE_FREQ.load(Ordering::Relaxed);
+    ns / NS_PER_SEC * freq + ns % NS_PER_SEC * freq / NS_PER_SEC
+}
+
+/// 时间片长度，单位为纳秒。
+static TIME_SLICE_NS: AtomicUsize = AtomicUsize::new(10_000_000);
+
+/// 设置时间片长度（毫秒），`None` 或无法解析时保持默认的 10ms。
+pub fn init_time_slice(ms: Option<&str>) {
+    if let Some(ms) = ms.and_then(|ms| ms.parse::<usize>().ok()).filter(|&ms| ms > 0) {
+        TIME_SLICE_NS.store(ms * 1_000_000, Ordering::Relaxed);
+    }
+}
+
+/// 开启 S-mode 定时器中断。
+pub fn init() {
+    unsafe { riscv::register::sie::set_stimer() };
+}
+
+/// 从现在开始计时一个时间片，到期时产生定时器中断。
+///
+/// 超时队列中有更早到期的线程时提前产生中断，让它尽快被唤醒。
+pub fn set_next_tick() {
+    let slice = ns_to_ticks(TIME_SLICE_NS.load(Ordering::Relaxed));
+    let mut next = riscv::register::time::read() + slice;
+    if let Some(deadline) = next_deadline() {
+        next = next.min(ns_to_ticks(deadline));
+    }
+    tg_sbi::set_timer(next as u64);
+}
+
+/// 按到期时刻排序的超时队列，每个阻塞的线程至多有一个超时。
+struct TimeoutQueue {
+    /// (到期时刻, 线程) -> 到期时执行的操作
+    queue: BTreeMap<(usize, ThreadId), Box<dyn FnOnce() + Send>>,
+    /// 线程 -> 到期时刻
+    deadlines: BTreeMap<ThreadId, usize>,
+}
+
+static TIMEOUTS: Mutex<TimeoutQueue> = Mutex::new(TimeoutQueue {
+    queue: BTreeMap::new(),
+    deadlines: BTreeMap::new(),
+});
+
+/// 为阻塞的线程 `tid` 设置超时，到期时在调度循环中执行 `action`。
+///
+/// `action` 负责把线程从它所在的等待队列中移出并唤醒。
+pub fn set_timeout(tid: ThreadId, deadline: usize, action: impl FnOnce() + Send + 'static) {
+    let mut timeouts = TIMEOUTS.lock();
+    if let Some(old) = timeouts.deadlines.insert(tid, deadline) {
+        timeouts.queue.remove(&(old, tid));
+    }
+    timeouts.queue.insert((deadline, tid), Box::new(action));
+}
+
+/// 线程在超时前被唤醒，取消它的超时。
+pub fn cancel_timeout(tid: ThreadId) {
+    let mut timeouts = TIMEOUTS.lock();
+    if let Some(deadline) = timeouts.deadlines.remove(&tid) {
+        timeouts.queue.remove(&(deadline, tid));
+    }
+}
+
+/// 按到期顺序执行所有已经到期的超时操作。
+pub fn check_timeout() {
+    let now = now_ns();
+    let mut expired = Vec::new();
+    {
+        let mut timeouts = TIMEOUTS.lock();
+        while let Some(entry) = timeouts.queue.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let ((_, tid), action) = entry.remove_entry();
+            timeouts.deadlines.remove(&tid);
+            expired.push(action);
+        }
+    }
+    // 超时操作会唤醒线程，不能持有锁执行
+    for action in expired {
+        action();
+    }
+}
+
+/// 最早的超时时刻，没有超时时返回 `None`。
+pub fn next_deadline() -> Option<usize> {
+    TIMEOUTS.lock().queue.keys().next().map(|&(deadline, _)| deadline)
+}
@@ -0,0 +1,143 @@
+//! 新程序的初始用户栈。
+//!
+//! 布局与 Linux 相同，从高地址到低地址依次为：字符串区（随机数、平台名、程序路径、环境变量、参数），
+//! 对齐到 16 字节之后是 auxv、envp、argv 和 argc，初始 `sp` 指向 argc。
+
+use crate::{build_flags, Sv39, Sv39Manager};
+use alloc::{string::String, vec::Vec};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use linux_raw_sys::auxvec::{AT_EXECFN, AT_NULL, AT_PLATFORM, AT_RANDOM};
+use tg_kernel_vm::{
+    page_table::{MmuMeta, VAddr, VmFlags},
+    AddressSpace,
+};
+
+/// AT_PLATFORM 指向的平台名
+const PLATFORM: &str = "riscv64";
+
+/// RISC-V 的 AT_HWCAP 按扩展字母置位，QEMU virt 为 rv64imafdc
+pub const HWCAP: usize = hwcap(b"imafdc");
+
+const fn hwcap(exts: &[u8]) -> usize {
+    let mut bits = 0;
+    let mut i = 0;
+    while i < exts.len() {
+        bits |= 1 << (exts[i] - b'a');
+        i += 1;
+    }
+    bits
+}
+
+/// 正在构建的用户栈，`sp` 从栈顶向下增长。
+pub struct UserStack<'a> {
+    /// 新程序的地址空间，栈所在的页按需分配
+    space: &'a mut AddressSpace<Sv39, Sv39Manager>,
+    /// 用户栈最低地址
+    bottom: usize,
+    sp: usize,
+}
+
+impl<'a> UserStack<'a> {
+    /// 用户栈占用 `space` 中的 `[bottom, top)`。
+    pub fn new(space: &'a mut AddressSpace<Sv39, Sv39Manager>, bottom: usize, top: usize) -> Self {
+        Self {
+            space,
+            bottom,
+            sp: top,
+        }
+    }
+
+    /// 放入参数、环境变量和 auxv，返回初始 `sp`，没有物理页存放栈的内容时返回 `None`。
+    ///
+    /// 除 `auxv` 外还会加入 AT_RANDOM、AT_EXECFN、AT_PLATFORM 和结尾的 AT_NULL。
+    pub fn init(
+        mut self,
+        execfn: &str,
+        args: &[String],
+        envs: &[String],
+        auxv: &[(u32, usize)],
+    ) -> Option<usize> {
+        let random = self.push_bytes(&random_bytes())?;
+        let platform = self.push_str(PLATFORM)?;
+        let execfn = self.push_str(execfn)?;
+        let envp: Vec<usize> = envs.iter().map(|env| self.push_str(env)).collect::<Option<_>>()?;
+        let argv: Vec<usize> = args.iter().map(|arg| self.push_str(arg)).collect::<Option<_>>()?;
+        self.sp &= !0xf;
+
+        let mut auxv = auxv.to_vec();
+        auxv.extend([
+            (AT_RANDOM, random),
+            (AT_EXECFN, execfn),
+            (AT_PLATFORM, platform),
+            (AT_NULL, 0),
+        ]);
+        // argc、以 0 结尾的 argv 和 envp、auxv 共占奇数个字时补一个字，使 sp 对齐到 16 字节
+        let words = 1 + (argv.len() + 1) + (envp.len() + 1) + auxv.len() * 2;
+        if words & 1 != 0 {
+            self.push_usize(0)?;
+        }
+        for &(key, value) in auxv.iter().rev() {
+            self.push_usize(value)?;
+            self.push_usize(key as usize)?;
+        }
+        self.push_usize(0)?;
+        for &env in envp.iter().rev() {
+            self.push_usize(env)?;
+        }
+        self.push_usize(0)?;
+        for &arg in argv.iter().rev() {
+            self.push_usize(arg)?;
+        }
+        self.push_usize(argv.len())?;
+        Some(self.sp)
+    }
+
+    /// 放入一段字节，返回其用户地址，栈所在的页无法分配时返回 `None`
+    fn push_bytes(&mut self, bytes: &[u8]) -> Option<usize> {
+        const PAGE_SIZE: usize = 1 << Sv39::PAGE_BITS;
+        const WRITEABLE: VmFlags<Sv39> = build_flags("W_V");
+        assert!(self.sp - self.bottom >= bytes.len(), "user stack overflow");
+        self.sp -= bytes.len();
+        // 栈上相邻的页不一定映射到相邻的物理页，逐页复制
+        let mut addr = self.sp;
+        let mut rest = bytes;
+        while !rest.is_empty() {
+            let len = (PAGE_SIZE - (addr & (PAGE_SIZE - 1))).min(rest.len());
+            let ptr = self.space.translate_mut::<u8>(VAddr::new(addr), WRITEABLE)?;
+            unsafe { core::ptr::copy_nonoverlapping(rest.as_ptr(), ptr.as_ptr(), len) };
+            addr += len;
+            rest = &rest[len..];
+        }
+        Some(self.sp)
+    }
+
+    /// 放入以 `\0` 结尾的字符串，返回其用户地址
+    fn push_str(&mut self, s: &str) -> Option<usize> {
+        self.push_bytes(&[0])?;
+        self.push_bytes(s.as_bytes())
+    }
+
+    fn push_usize(&mut self, value: usize) -> Option<usize> {
+        self.push_bytes(&value.to_ne_bytes())
+    }
+}
+
+/// AT_RANDOM 的 16 字节随机数。
+///
+/// 以 `time` 寄存器和调用次数为种子的 splitmix64，足以让每个进程的栈保护值不同，但不能用于密码学。
+fn random_bytes() -> [u8; 16] {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut state =
+        riscv::register::time::read() as u64 ^ (COUNTER.fetch_add(1, Ordering::Relaxed) as u64) << 32;
+    let mut next = || {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&next().to_le_bytes());
+    bytes[8..].copy_from_slice(&next().to_le_bytes());
+    bytes
+}
@@ -1,15 +1,18 @@
-use crate::{build_flags, Sv39, KERNEL_SPACE};
-use alloc::{
-    alloc::{alloc_zeroed, dealloc},
-    sync::Arc,
+use crate::{
+    build_flags,
+    frame::{self, FrameKind},
+    Sv39, KERNEL_SPACE,
 };
-use core::{alloc::Layout, ptr::NonNull};
+use alloc::sync::Arc;
+use core::ptr::NonNull;
 use spin::{Lazy, Mutex};
 use tg_easy_fs::BlockDevice;
 use tg_kernel_vm::page_table::{MmuMeta, VAddr, VmFlags};
 use virtio_drivers::{Hal, MmioTransport, VirtIOBlk, VirtIOHeader};
 
 const VIRTIO0: usize = 0x10001000;
+/// 交换设备，QEMU 的 `virtio-mmio-bus.1`
+const VIRTIO1: usize = 0x10002000;
 
 pub static BLOCK_DEVICE: Lazy<Arc<dyn BlockDevice>> = Lazy::new(|| {
     Arc::new(unsafe {
@@ -23,6 +26,14 @@ pub static BLOCK_DEVICE: Lazy<Arc<dyn BlockDevice>> = Lazy::new(|| {
     })
 });
 
+/// 交换设备，没有挂载第二块磁盘时为 `None`。
+pub static SWAP_DEVICE: Lazy<Option<Arc<dyn BlockDevice>>> = Lazy::new(|| {
+    let transport =
+        unsafe { MmioTransport::new(NonNull::new(VIRTIO1 as *mut VirtIOHeader).unwrap()) }.ok()?;
+    let blk = VirtIOBlk::new(transport).ok()?;
+    Some(Arc::new(VirtIOBlock(Mutex::new(blk))))
+});
+
 struct VirtIOBlock(Mutex<VirtIOBlk<VirtioHal, MmioTransport>>);
 
 // Safety: VirtIOBlock 内部使用 Mutex 保护，确保线程安全访问
@@ -49,22 +60,12 @@ struct VirtioHal;
 impl Hal for VirtioHal {
     fn dma_alloc(pages: usize) -> usize {
         // warn!("dma_alloc");
-        unsafe {
-            alloc_zeroed(Layout::from_size_align_unchecked(
-                pages << Sv39::PAGE_BITS,
-                1 << Sv39::PAGE_BITS,
-            )) as _
-        }
+        frame::alloc(pages, FrameKind::Dma).expect("out of physical frames") << Sv39::PAGE_BITS
     }
 
     fn dma_dealloc(paddr: usize, pages: usize) -> i32 {
         // warn!("dma_dealloc");
-        unsafe {
-            dealloc(
-                paddr as _,
-                Layout::from_size_align_unchecked(pages << Sv39::PAGE_BITS, 1 << Sv39::PAGE_BITS),
-            )
-        }
+        frame::dealloc(paddr >> Sv39::PAGE_BITS, pages);
         0
     }
 
@@ -5,16 +5,29 @@
 
 mod space;
 
+extern crate alloc;
+
 pub extern crate page_table;
 pub use space::AddressSpace;
 
 use core::ptr::NonNull;
 use page_table::{Pte, VmFlags, VmMeta, PPN};
 
+/// 按需分配的页的内容来源，例如文件映射。
+pub trait PageSource {
+    /// 把第 `index` 页的内容读入已清零的 `page`。
+    fn read_page(&self, index: usize, page: &mut [u8]);
+
+    /// 把 `page` 写回第 `index` 页，只用于共享映射。
+    fn write_page(&self, index: usize, page: &[u8]);
+}
+
 /// 物理页管理。
 pub trait PageManager<Meta: VmMeta> {
-    /// 新建根页表页。
-    fn new_root() -> Self;
+    /// 新建根页表页，没有空闲物理页时返回 `None`。
+    fn new_root() -> Option<Self>
+    where
+        Self: Sized;
 
     /// 获取根页表。
     fn root_ptr(&self) -> NonNull<Pte<Meta>>;
@@ -31,15 +44,45 @@ pub trait PageManager<Meta: VmMeta> {
     /// 计算当前地址空间上的指针指向的物理页。
     fn v_to_p<T>(&self, ptr: NonNull<T>) -> PPN<Meta>;
 
+    /// 写时复制页的软件标志位。
+    const COW: VmFlags<Meta>;
+
+    /// 可写标志位，写时复制页去掉此位映射为只读。
+    const WRITABLE: VmFlags<Meta>;
+
+    /// 访问标志位，页被访问时由硬件置位。
+    const ACCESSED: VmFlags<Meta>;
+
+    /// 脏标志位，页被写入时由硬件置位。
+    const DIRTY: VmFlags<Meta>;
+
     /// 检查是否拥有一个页的所有权。
     fn check_owned(&self, pte: Pte<Meta>) -> bool;
 
-    /// 为地址空间分配 `len` 个物理页。
-    fn allocate(&mut self, len: usize, flags: &mut VmFlags<Meta>) -> NonNull<u8>;
+    /// 为地址空间分配 `len` 个物理页，没有空闲物理页时返回 `None`。
+    fn allocate(&mut self, len: usize, flags: &mut VmFlags<Meta>) -> Option<NonNull<u8>>;
 
-    /// 从地址空间释放 `pte` 指示的 `len` 个物理页。
+    /// 减少 `pte` 指示的 `len` 个物理页的引用，释放不再被引用的页，返回释放的页数。
     fn deallocate(&mut self, pte: Pte<Meta>, len: usize) -> usize;
 
+    /// 增加 `pte` 指示的物理页的一个引用，用于写时复制共享。
+    fn share(&self, pte: Pte<Meta>);
+
+    /// `pte` 指示的物理页是否被多个地址空间引用。
+    fn is_shared(&self, pte: Pte<Meta>) -> bool;
+
+    /// 把 `pte` 指示的页写入交换区并释放，返回代替它的无效页表项；交换区已满时返回 `None`。
+    ///
+    /// 返回的页表项保留除有效位以外的页属性，使 [`AddressSpace`] 能识别换出的页。
+    /// `dirty` 为 `false` 时页的内容与上次换入时相同。
+    fn swap_out(&mut self, pte: Pte<Meta>, dirty: bool) -> Option<Pte<Meta>>;
+
+    /// 为换出的页 `pte` 分配物理页并读回内容，返回有效的页表项；没有空闲物理页时返回 `None`。
+    fn swap_in(&mut self, pte: Pte<Meta>) -> Option<Pte<Meta>>;
+
+    /// 丢弃换出的页 `pte` 在交换区中的内容。
+    fn swap_free(&mut self, pte: Pte<Meta>);
+
     /// 释放根页表。
     fn drop_root(&mut self);
 }
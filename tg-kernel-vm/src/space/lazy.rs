@@ -0,0 +1,42 @@
+use crate::PageSource;
+use alloc::sync::Arc;
+use core::ops::Range;
+use page_table::{VmFlags, VmMeta, VPN};
+
+/// 按需分配物理页的虚拟地址块。
+#[derive(Clone)]
+pub(super) struct LazyArea<Meta: VmMeta> {
+    pub range: Range<VPN<Meta>>,
+    pub flags: VmFlags<Meta>,
+    /// 共享映射：fork 时父子进程共享物理页而不做写时复制，取消映射时写回内容来源
+    pub shared: bool,
+    /// 页的内容来源，以及 `range.start` 对应的页序号
+    pub source: Option<(Arc<dyn PageSource>, usize)>,
+}
+
+impl<Meta: VmMeta> LazyArea<Meta> {
+    /// 截取与 `range` 重叠的部分，不重叠时返回 `None`。
+    pub fn slice(&self, range: &Range<VPN<Meta>>) -> Option<Self> {
+        let start = if range.start > self.range.start { range.start } else { self.range.start };
+        let end = if range.end < self.range.end { range.end } else { self.range.end };
+        if start >= end {
+            return None;
+        }
+        Some(Self {
+            range: start..end,
+            flags: self.flags,
+            shared: self.shared,
+            source: self
+                .source
+                .as_ref()
+                .map(|(source, first)| (source.clone(), first + (start.val() - self.range.start.val()))),
+        })
+    }
+
+    /// `vpn` 在内容来源中的页序号。
+    pub fn source_index(&self, vpn: VPN<Meta>) -> Option<(&Arc<dyn PageSource>, usize)> {
+        self.source
+            .as_ref()
+            .map(|(source, first)| (source, first + (vpn.val() - self.range.start.val())))
+    }
+}
@@ -1,11 +1,15 @@
-﻿use crate::{AddressSpace, PageManager};
+﻿use super::level_pages;
+use crate::{AddressSpace, PageManager};
+use alloc::vec::Vec;
 use core::{ops::Range, ptr::NonNull};
-use page_table::{Decorator, Pos, Pte, Update, VmFlags, VmMeta, PPN};
+use page_table::{Decorator, MmuMeta, Pos, Pte, Update, VmFlags, VmMeta, PPN, VPN};
 
 pub(super) struct Mapper<'a, Meta: VmMeta, M: PageManager<Meta>> {
     space: &'a mut AddressSpace<Meta, M>,
     range: Range<PPN<Meta>>,
     flags: VmFlags<Meta>,
+    /// 预先分配的页表页，映射过程中不再分配物理页
+    tables: Vec<(NonNull<u8>, VmFlags<Meta>)>,
     done: bool,
 }
 
@@ -15,32 +19,56 @@ impl<'a, Meta: VmMeta, M: PageManager<Meta>> Mapper<'a, Meta, M> {
         space: &'a mut AddressSpace<Meta, M>,
         range: Range<PPN<Meta>>,
         flags: VmFlags<Meta>,
+        tables: Vec<(NonNull<u8>, VmFlags<Meta>)>,
     ) -> Self {
         Self {
             space,
             range,
             flags,
+            tables,
             done: false,
         }
     }
 
+    /// 是否映射完成，以及没有用到的页表页。
     #[inline]
-    pub fn ans(self) -> bool {
-        self.done
+    pub fn ans(self) -> (bool, Vec<(NonNull<u8>, VmFlags<Meta>)>) {
+        (self.done, self.tables)
+    }
+
+    /// 从 `vpn` 开始映射时的目标：虚页号、物理页号和剩余页数都允许时使用尽量大的页。
+    pub fn target(&self, vpn: VPN<Meta>) -> Pos<Meta> {
+        let mut level = 0;
+        if Meta::is_leaf(self.flags.val()) {
+            let rest = self.range.end.val() - self.range.start.val();
+            while level < Meta::MAX_LEVEL {
+                let pages = level_pages::<Meta>(level + 1);
+                if vpn.val() % pages != 0 || self.range.start.val() % pages != 0 || rest < pages {
+                    break;
+                }
+                level += 1;
+            }
+        }
+        Pos::new(vpn, level)
     }
 }
 
 impl<Meta: VmMeta, M: PageManager<Meta>> Decorator<Meta> for Mapper<'_, Meta, M> {
     #[inline]
     fn arrive(&mut self, pte: &mut Pte<Meta>, target_hint: Pos<Meta>) -> Pos<Meta> {
+        if target_hint.level > 0 && pte.is_valid() && !Meta::is_leaf(pte.flags().val()) {
+            // 已有下级页表，改用小一级的页
+            return Pos::new(target_hint.vpn, target_hint.level - 1);
+        }
         assert!(!pte.is_valid());
         *pte = self.flags.build_pte(self.range.start);
-        self.range.start += 1;
+        let pages = level_pages::<Meta>(target_hint.level);
+        self.range.start += pages;
         if self.range.start == self.range.end {
             self.done = true;
             Pos::stop()
         } else {
-            target_hint.next()
+            self.target(target_hint.vpn + pages)
         }
     }
 
@@ -51,7 +79,8 @@ impl<Meta: VmMeta, M: PageManager<Meta>> Decorator<Meta> for Mapper<'_, Meta, M>
         pte: Pte<Meta>,
         _target_hint: Pos<Meta>,
     ) -> Option<NonNull<Pte<Meta>>> {
-        if self.space.page_manager.check_owned(pte) {
+        // 大页不能作为下级页表
+        if self.space.page_manager.check_owned(pte) && !Meta::is_leaf(pte.flags().val()) {
             Some(self.space.page_manager.p_to_v(pte.ppn()))
         } else {
             None
@@ -61,8 +90,8 @@ impl<Meta: VmMeta, M: PageManager<Meta>> Decorator<Meta> for Mapper<'_, Meta, M>
     #[inline]
     fn block(&mut self, _level: usize, pte: Pte<Meta>, _target_hint: Pos<Meta>) -> Update<Meta> {
         assert!(!pte.is_valid());
-        let mut flags = VmFlags::VALID;
-        let page = self.space.page_manager.allocate(1, &mut flags);
+        // 预留的页表页按最坏情况计算，不会用完
+        let (page, flags) = self.tables.pop().unwrap();
         let ppn = self.space.page_manager.v_to_p(page);
         Update::Pte(flags.build_pte(ppn), page.cast())
     }
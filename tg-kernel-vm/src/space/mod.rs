@@ -1,30 +1,36 @@
+mod lazy;
 mod mapper;
 mod visitor;
 
-extern crate alloc;
-
-use crate::PageManager;
-use alloc::vec::Vec;
+use crate::{PageManager, PageSource};
+use alloc::{sync::Arc, vec::Vec};
 use core::{fmt, ops::Range, ptr::NonNull};
+use lazy::LazyArea;
 use mapper::Mapper;
-use page_table::{PageTable, PageTableFormatter, Pos, VAddr, VmFlags, VmMeta, PPN, VPN};
+use page_table::{PageTable, PageTableFormatter, Pos, Pte, VAddr, VmFlags, VmMeta, PPN, VPN};
 use visitor::Visitor;
 
 /// 地址空间。
 pub struct AddressSpace<Meta: VmMeta, M: PageManager<Meta>> {
     /// 虚拟地址块
     pub areas: Vec<Range<VPN<Meta>>>,
+    /// 按需分配物理页的虚拟地址块
+    lazy_areas: Vec<LazyArea<Meta>>,
+    /// [`Self::reclaim`] 的时钟指针
+    clock: VPN<Meta>,
     page_manager: M,
 }
 
 impl<Meta: VmMeta, M: PageManager<Meta>> AddressSpace<Meta, M> {
-    /// 创建新地址空间。
+    /// 创建新地址空间，没有空闲物理页存放根页表时返回 `None`。
     #[inline]
-    pub fn new() -> Self {
-        Self {
+    pub fn new() -> Option<Self> {
+        Some(Self {
             areas: Vec::new(),
-            page_manager: M::new_root(),
-        }
+            lazy_areas: Vec::new(),
+            clock: VPN::new(0),
+            page_manager: M::new_root()?,
+        })
     }
 
     /// 地址空间根页表的物理页号。
@@ -41,44 +47,237 @@ impl<Meta: VmMeta, M: PageManager<Meta>> AddressSpace<Meta, M> {
         unsafe { PageTable::from_root(self.page_manager.root_ptr()) }
     }
 
-    /// 向地址空间增加映射关系。
-    pub fn map_extern(&mut self, range: Range<VPN<Meta>>, pbase: PPN<Meta>, flags: VmFlags<Meta>) {
-        self.areas.push(range.start..range.end);
+    /// 向地址空间增加映射关系，页表页不足时返回 `false`，不做任何映射。
+    pub fn map_extern(
+        &mut self,
+        range: Range<VPN<Meta>>,
+        pbase: PPN<Meta>,
+        flags: VmFlags<Meta>,
+    ) -> bool {
+        if !self.map_pages(range.clone(), pbase, flags) {
+            return false;
+        }
+        self.areas.push(range);
+        true
+    }
+
+    /// 建立页表映射，不记录虚拟地址块。
+    ///
+    /// 缺少的页表页先全部分配好再修改页表，物理页不足时返回 `false`，页表不变。
+    fn map_pages(
+        &mut self,
+        range: Range<VPN<Meta>>,
+        pbase: PPN<Meta>,
+        flags: VmFlags<Meta>,
+    ) -> bool {
+        let Some(tables) = self.alloc_tables(&range) else {
+            return false;
+        };
         let count = range.end.val() - range.start.val();
         let mut root = self.root();
-        let mut mapper = Mapper::new(self, pbase..pbase + count, flags);
-        root.walk_mut(Pos::new(range.start, 0), &mut mapper);
-        if !mapper.ans() {
-            // 映射失败，需要回滚吗？
-            todo!()
+        let mut mapper = Mapper::new(self, pbase..pbase + count, flags, tables);
+        let start = mapper.target(range.start);
+        root.walk_mut(start, &mut mapper);
+        let (done, tables) = mapper.ans();
+        // 用大页映射时预留的页表页用不完
+        self.free_tables(tables);
+        // 缺少的页表页已全部预留，Mapper 不会中途失败，遍历只在整个范围映射完后停止
+        debug_assert!(done);
+        true
+    }
+
+    /// 为映射 `range` 分配缺少的页表页，物理页不足时释放已分配的页并返回 `None`。
+    fn alloc_tables(
+        &mut self,
+        range: &Range<VPN<Meta>>,
+    ) -> Option<Vec<(NonNull<u8>, VmFlags<Meta>)>> {
+        let mut tables = Vec::new();
+        for level in 1..=Meta::MAX_LEVEL {
+            // 第 `level` 级的每个页表项需要一个下级页表
+            let pages = level_pages::<Meta>(level);
+            let mut vpn = range.start.val() & !(pages - 1);
+            while vpn < range.end.val() {
+                if !self.has_table(VPN::new(vpn), level) {
+                    let mut flags = VmFlags::VALID;
+                    let Some(page) = self.page_manager.allocate(1, &mut flags) else {
+                        self.free_tables(tables);
+                        return None;
+                    };
+                    tables.push((page, flags));
+                }
+                vpn += pages;
+            }
+        }
+        Some(tables)
+    }
+
+    /// 释放 [`Self::alloc_tables`] 分配而没有用到的页表页。
+    fn free_tables(&mut self, tables: Vec<(NonNull<u8>, VmFlags<Meta>)>) {
+        for (page, flags) in tables {
+            let pte = flags.build_pte(self.page_manager.v_to_p(page));
+            self.page_manager.deallocate(pte, 1);
         }
     }
 
+    /// `vpn` 在第 `level` 级的页表项是否指向下级页表。
+    fn has_table(&self, vpn: VPN<Meta>, level: usize) -> bool {
+        let mut current = self.page_manager.root_ptr();
+        for l in (level..=Meta::MAX_LEVEL).rev() {
+            // SAFETY: current 是本地址空间中有效的页表页，索引不超过页表项数
+            let pte = unsafe { *current.as_ptr().add(vpn.index_in(l)) };
+            if !pte.is_valid() || <Meta as page_table::MmuMeta>::is_leaf(pte.flags().val()) {
+                return false;
+            }
+            current = self.page_manager.p_to_v(pte.ppn());
+        }
+        true
+    }
+
     /// 分配新的物理页，拷贝数据并建立映射。
+    ///
+    /// 物理页逐页分配，以便取消映射时逐页释放。物理页不足时返回 `false`，已经映射的页保留在地址空间中。
     pub fn map(
         &mut self,
         range: Range<VPN<Meta>>,
         data: &[u8],
         offset: usize,
-        mut flags: VmFlags<Meta>,
-    ) {
+        flags: VmFlags<Meta>,
+    ) -> bool {
+        let page_size = 1 << Meta::PAGE_BITS;
         let count = range.end.val() - range.start.val();
-        let size = count << Meta::PAGE_BITS;
-        assert!(size >= data.len() + offset);
-        let page = self.page_manager.allocate(count, &mut flags);
-        // SAFETY: page 是刚分配的有效内存，大小为 size 字节。
-        // 我们按顺序填充：[0, offset) 清零，[offset, offset+data.len()) 拷贝数据，
-        // [offset+data.len(), size) 清零。
-        unsafe {
-            use core::slice::from_raw_parts_mut as slice;
-            let mut ptr = page.as_ptr();
-            slice(ptr, offset).fill(0);
-            ptr = ptr.add(offset);
-            slice(ptr, data.len()).copy_from_slice(data);
-            ptr = ptr.add(data.len());
-            slice(ptr, page.as_ptr().add(size).offset_from(ptr) as _).fill(0);
-        }
-        self.map_extern(range, self.page_manager.v_to_p(page), flags)
+        assert!(count * page_size >= data.len() + offset);
+        self.areas.push(range.clone());
+        let mut vpn = range.start;
+        // 当前页在整个范围中的偏移，`[offset, offset + data.len())` 以外的部分清零
+        let mut pos = 0;
+        while vpn < range.end {
+            let mut page_flags = flags;
+            let Some(page) = self.page_manager.allocate(1, &mut page_flags) else {
+                return false;
+            };
+            // SAFETY: page 是刚分配的一页有效内存
+            let buf = unsafe { core::slice::from_raw_parts_mut(page.as_ptr(), page_size) };
+            buf.fill(0);
+            let start = offset.max(pos);
+            let end = (offset + data.len()).min(pos + page_size);
+            if start < end {
+                buf[start - pos..end - pos].copy_from_slice(&data[start - offset..end - offset]);
+            }
+            let ppn = self.page_manager.v_to_p(page);
+            if !self.map_pages(vpn..vpn + 1, ppn, page_flags) {
+                self.page_manager.deallocate(page_flags.build_pte(ppn), 1);
+                return false;
+            }
+            pos += page_size;
+            vpn = vpn + 1;
+        }
+        true
+    }
+
+    /// 向地址空间增加按需分配的虚拟地址块，物理页在首次访问时由 [`Self::populate`] 分配。
+    pub fn map_lazy(&mut self, range: Range<VPN<Meta>>, flags: VmFlags<Meta>) {
+        self.map_backed(range, flags, None, false);
+    }
+
+    /// 向地址空间增加按需分配的虚拟地址块，页的初始内容从 `source` 的第 0 页起依次读入。
+    ///
+    /// `shared` 的虚拟地址块在 fork 时不做写时复制，[`Self::sync`] 和取消映射时把内容写回 `source`。
+    pub fn map_backed(
+        &mut self,
+        range: Range<VPN<Meta>>,
+        flags: VmFlags<Meta>,
+        source: Option<Arc<dyn PageSource>>,
+        shared: bool,
+    ) {
+        self.areas.push(range.clone());
+        self.lazy_areas.push(LazyArea {
+            range,
+            flags,
+            shared,
+            source: source.map(|source| (source, 0)),
+        });
+    }
+
+    /// `addr` 位于按需分配的虚拟地址块中且所在页尚未映射时，分配物理页、读入初始内容并映射，返回 `true`。
+    ///
+    /// 没有内容来源的页清零。页属性不可读、写、执行（`PROT_NONE`）的虚拟地址块只占位，不会分配物理页。
+    /// 物理页不足时同样返回 `false`。
+    pub fn populate(&mut self, addr: VAddr<Meta>) -> bool {
+        let vpn = addr.floor();
+        // 换出的页从交换区读回
+        if let Some((pte_ptr, 0)) = self.find_pte(vpn) {
+            // SAFETY: find_pte 返回本地址空间页表中的有效页表项指针
+            let pte = unsafe { *pte_ptr };
+            if is_swapped(pte) {
+                let Some(pte) = self.page_manager.swap_in(pte) else {
+                    return false;
+                };
+                unsafe { *pte_ptr = pte };
+                return true;
+            }
+        }
+        let Some(area) = self
+            .lazy_areas
+            .iter()
+            .find(|area| area.range.contains(&vpn))
+            .filter(|area| <Meta as page_table::MmuMeta>::is_leaf(area.flags.val()))
+        else {
+            return false;
+        };
+        // SAFETY: find_pte 返回本地址空间页表中的有效页表项指针
+        if self
+            .find_pte(vpn)
+            .is_some_and(|(pte_ptr, _)| unsafe { *pte_ptr }.is_valid())
+        {
+            return false;
+        }
+        let mut flags = area.flags;
+        let source = area.source_index(vpn).map(|(source, index)| (source.clone(), index));
+        let Some(page) = self.page_manager.allocate(1, &mut flags) else {
+            return false;
+        };
+        // SAFETY: page 是刚分配的一页有效内存
+        let data = unsafe { core::slice::from_raw_parts_mut(page.as_ptr(), 1 << Meta::PAGE_BITS) };
+        data.fill(0);
+        if let Some((source, index)) = source {
+            source.read_page(index, data);
+        }
+        let ppn = self.page_manager.v_to_p(page);
+        if !self.map_pages(vpn..vpn + 1, ppn, flags) {
+            self.page_manager.deallocate(flags.build_pte(ppn), 1);
+            return false;
+        }
+        true
+    }
+
+    /// 把 `range` 中共享映射已分配的页写回内容来源。
+    pub fn sync(&self, range: Range<VPN<Meta>>) {
+        for area in self.lazy_areas.iter().filter_map(|area| area.slice(&range)) {
+            if !area.shared {
+                continue;
+            }
+            let mut vpn = area.range.start;
+            while vpn < area.range.end {
+                // SAFETY: find_pte 返回本地址空间页表中的有效页表项指针，共享映射不使用大页
+                let pte = self
+                    .find_pte(vpn)
+                    .filter(|&(_, level)| level == 0)
+                    .map(|(pte_ptr, _)| unsafe { *pte_ptr });
+                if let (Some(pte), Some((source, index))) = (pte, area.source_index(vpn)) {
+                    if pte.is_valid() {
+                        // SAFETY: pte 指向本地址空间中有效的一页物理内存
+                        let data = unsafe {
+                            core::slice::from_raw_parts(
+                                self.page_manager.p_to_v::<u8>(pte.ppn()).as_ptr(),
+                                1 << Meta::PAGE_BITS,
+                            )
+                        };
+                        source.write_page(index, data);
+                    }
+                }
+                vpn = vpn + 1;
+            }
+        }
     }
 
     /// 取消指定 VPN 范围的映射
@@ -100,12 +299,27 @@ impl<Meta: VmMeta, M: PageManager<Meta>> AddressSpace<Meta, M> {
             }
         }
         self.areas = new_areas;
+        // 共享映射的修改先写回，按需分配的虚拟地址块同样拆分
+        self.sync(range.clone());
+        let mut new_lazy_areas = Vec::new();
+        for area in self.lazy_areas.drain(..) {
+            new_lazy_areas.extend(area.slice(&(area.range.start..range.start)));
+            new_lazy_areas.extend(area.slice(&(range.end..area.range.end)));
+        }
+        self.lazy_areas = new_lazy_areas;
 
         // 清除页表项（将 PTE 设为无效，即写入 0）
         let mut vpn = range.start;
         while vpn < range.end {
             // 使用 visitor 找到 PTE 并清除
             if let Some(pte_ptr) = self.find_pte_mut(vpn) {
+                let pte = unsafe { *pte_ptr };
+                // 写时复制共享的页少一个引用，没有其他引用时释放
+                if pte.is_valid() && self.page_manager.check_owned(pte) {
+                    self.page_manager.deallocate(pte, 1);
+                } else if is_swapped(pte) {
+                    self.page_manager.swap_free(pte);
+                }
                 unsafe {
                     core::ptr::write_bytes(
                         pte_ptr as *mut u8,
@@ -117,41 +331,137 @@ impl<Meta: VmMeta, M: PageManager<Meta>> AddressSpace<Meta, M> {
             vpn = vpn + 1;
         }
 
-        // 刷新地址空间
-        #[cfg(target_arch = "riscv64")]
-        unsafe {
-            core::arch::asm!("sfence.vma")
-        };
+        flush_tlb();
     }
 
-    /// 查找指定 VPN 的 PTE 指针（用于修改）
-    fn find_pte_mut(&self, vpn: VPN<Meta>) -> Option<*mut page_table::Pte<Meta>> {
-        let mut current = self.page_manager.root_ptr();
+    /// 把 `range` 中的页属性改为 `flags` 并刷新 TLB，部分覆盖的虚拟地址块被拆分，之后按需分配的页也使用新属性。
+    ///
+    /// 与其他地址空间共享的页即使改为可写也保持只读并标记为写时复制；
+    /// 改为不可访问的已分配页保留为用户态不可访问的只读页，以免丢失其内容。
+    pub fn protect(&mut self, range: Range<VPN<Meta>>, flags: VmFlags<Meta>) {
+        let mut new_areas = Vec::new();
+        for area in self.areas.drain(..) {
+            if area.end <= range.start || area.start >= range.end {
+                new_areas.push(area);
+                continue;
+            }
+            let start = if area.start > range.start { area.start } else { range.start };
+            let end = if area.end < range.end { area.end } else { range.end };
+            if area.start < start {
+                new_areas.push(area.start..start);
+            }
+            new_areas.push(start..end);
+            if end < area.end {
+                new_areas.push(end..area.end);
+            }
+        }
+        self.areas = new_areas;
+        let mut new_lazy_areas = Vec::new();
+        for area in self.lazy_areas.drain(..) {
+            new_lazy_areas.extend(area.slice(&(area.range.start..range.start)));
+            if let Some(mut middle) = area.slice(&range) {
+                middle.flags = flags;
+                new_lazy_areas.push(middle);
+            }
+            new_lazy_areas.extend(area.slice(&(range.end..area.range.end)));
+        }
+        self.lazy_areas = new_lazy_areas;
 
+        let leaf = <Meta as page_table::MmuMeta>::is_leaf(flags.val());
+        let mut vpn = range.start;
+        while vpn < range.end {
+            if let Some(pte_ptr) = self.find_pte_mut(vpn) {
+                // SAFETY: find_pte_mut 返回本地址空间页表中的有效页表项指针
+                let pte = unsafe { *pte_ptr };
+                if is_swapped(pte) {
+                    // 换出的页只记录新的属性，换入时生效
+                    let mut bits = pte.flags().val() & !PERMISSION_BITS;
+                    bits |= if leaf { flags.val() & PERMISSION_BITS } else { NO_ACCESS_BITS };
+                    bits &= !VmFlags::<Meta>::VALID.val();
+                    // SAFETY: 只改动页属性，交换区中的位置不变
+                    unsafe { *pte_ptr = VmFlags::from_raw(bits).build_pte(pte.ppn()) };
+                } else if pte.is_valid() {
+                    let old = pte.flags().val();
+                    let mut bits = old & !PERMISSION_BITS & !M::COW.val();
+                    bits |= if leaf { flags.val() & PERMISSION_BITS } else { NO_ACCESS_BITS };
+                    let shared_area = self
+                        .lazy_areas
+                        .iter()
+                        .any(|area| area.shared && area.range.contains(&vpn));
+                    if bits & M::WRITABLE.val() != 0
+                        && !shared_area
+                        && (old & M::COW.val() != 0 || self.page_manager.is_shared(pte))
+                    {
+                        bits = (bits & !M::WRITABLE.val()) | M::COW.val();
+                    }
+                    // SAFETY: 只改动权限位和 COW 标志位，物理页不变
+                    unsafe { *pte_ptr = VmFlags::from_raw(bits).build_pte(pte.ppn()) };
+                }
+            }
+            vpn = vpn + 1;
+        }
+        flush_tlb();
+    }
+
+    /// 查找 `vpn` 所在的叶子页表项指针及其级别，大页的页表项在较高的级别。
+    ///
+    /// 0 级的页表项即使无效也返回，较高级别的页表项无效时返回 `None`。
+    fn find_pte(&self, vpn: VPN<Meta>) -> Option<(*mut Pte<Meta>, usize)> {
+        let mut current = self.page_manager.root_ptr();
         for level in (0..=Meta::MAX_LEVEL).rev() {
             let idx = vpn.index_in(level);
             let pte_ptr = unsafe { current.as_ptr().add(idx) };
             let pte = unsafe { *pte_ptr };
-
             if level == 0 {
-                return Some(pte_ptr);
+                return Some((pte_ptr, 0));
             }
-
             if !pte.is_valid() {
                 return None;
             }
+            if <Meta as page_table::MmuMeta>::is_leaf(pte.flags().val()) {
+                return Some((pte_ptr, level));
+            }
+            current = self.page_manager.p_to_v(pte.ppn());
+        }
+        None
+    }
 
-            // 如果是叶子节点（大页），也返回
-            // 检查 R 或 X 位来判断是否是叶子节点
-            let flags_raw = pte.flags().val();
-            let is_leaf = (flags_raw & 0b1010) != 0; // R=bit1, X=bit3
-            if is_leaf {
+    /// 查找 `vpn` 的 0 级页表项指针（用于修改），途经的大页先拆分为下一级的页。
+    ///
+    /// 没有物理页存放拆分出的页表时返回 `None`。
+    fn find_pte_mut(&mut self, vpn: VPN<Meta>) -> Option<*mut Pte<Meta>> {
+        loop {
+            let (pte_ptr, level) = self.find_pte(vpn)?;
+            if level == 0 {
                 return Some(pte_ptr);
             }
+            if !self.split(pte_ptr, level) {
+                return None;
+            }
+        }
+    }
 
-            current = self.page_manager.p_to_v(pte.ppn());
+    /// 把第 `level` 级的大页页表项替换为下一级页表，其中的页映射原来的物理页，属性不变。
+    ///
+    /// 物理页不足时返回 `false`，大页保持不变。
+    fn split(&mut self, pte_ptr: *mut Pte<Meta>, level: usize) -> bool {
+        // SAFETY: pte_ptr 是本地址空间页表中有效的大页页表项
+        let pte = unsafe { *pte_ptr };
+        let step = level_pages::<Meta>(level - 1);
+        let entries = level_pages::<Meta>(1);
+        let mut flags = VmFlags::VALID;
+        let Some(table) = self.page_manager.allocate(1, &mut flags) else {
+            return false;
+        };
+        let table = table.cast::<Pte<Meta>>();
+        for i in 0..entries {
+            // SAFETY: table 是刚分配的一页页表
+            unsafe { *table.as_ptr().add(i) = pte.flags().build_pte(pte.ppn() + i * step) };
         }
-        None
+        // SAFETY: 新页表与原来的大页映射相同的地址
+        unsafe { *pte_ptr = flags.build_pte(self.page_manager.v_to_p(table)) };
+        flush_tlb();
+        true
     }
 
     /// 检查 `flags` 的属性要求，然后将地址空间中的一个虚地址翻译成当前地址空间中的指针。
@@ -178,45 +488,268 @@ impl<Meta: VmMeta, M: PageManager<Meta>> AddressSpace<Meta, M> {
             })
     }
 
-    /// 遍历地址空间，将其中的地址映射添加进自己的地址空间中，重新分配物理页并拷贝所有数据及代码
-    pub fn cloneself(&self, new_addrspace: &mut AddressSpace<Meta, M>) {
-        let root = self.root();
-        let areas = &self.areas;
-        for (_, range) in areas.iter().enumerate() {
-            let mut visitor = Visitor::new(self);
-            // 虚拟地址块的首地址的 vpn
-            let vpn = range.start;
-            // 利用 visitor 访问页表，并获取这个虚拟地址块的页属性
-            root.walk(Pos::new(vpn, 0), &mut visitor);
-            // 利用 visitor 获取这个虚拟地址块的页属性，以及起始地址
-            let (mut flags, mut data_ptr) = visitor
-                .ans()
-                .filter(|pte| pte.is_valid())
-                .map(|pte| {
-                    // SAFETY: pte 是有效的页表项，p_to_v 返回有效的指针
-                    (pte.flags(), unsafe {
-                        NonNull::new_unchecked(self.page_manager.p_to_v::<u8>(pte.ppn()).as_ptr())
-                    })
-                })
-                .unwrap();
-            let vpn_range = range.start..range.end;
-            // 虚拟地址块中页数量
-            let count = range.end.val() - range.start.val();
-            let size = count << Meta::PAGE_BITS;
-            // 分配 count 个 flags 属性的物理页面
-            let paddr = new_addrspace.page_manager.allocate(count, &mut flags);
-            let ppn = new_addrspace.page_manager.v_to_p(paddr);
-            // SAFETY: data_ptr 指向源地址空间中 size 字节的有效数据，
-            // paddr 指向新分配的 size 字节内存，两者不重叠
+    /// 遍历地址空间，以写时复制的方式将其中的地址映射添加进 `new_addrspace`。
+    ///
+    /// 两个地址空间映射相同的物理页并增加其引用计数，可写的页在双方都改为只读并打上 `M::COW` 标记，
+    /// 直到某一方写入时由 [`Self::copy_on_write`] 复制；共享映射的页保持可写。
+    ///
+    /// 物理页不足时返回 `false`，`new_addrspace` 只复制了一部分，由调用者丢弃。
+    pub fn cloneself(&mut self, new_addrspace: &mut AddressSpace<Meta, M>) -> bool {
+        new_addrspace.lazy_areas = self.lazy_areas.clone();
+        for range in self.areas.clone() {
+            new_addrspace.areas.push(range.clone());
+            let mut vpn = range.start;
+            while vpn < range.end {
+                let mut count = 1;
+                // 换出的页先换入，再与新地址空间共享
+                if self
+                    .find_pte(vpn)
+                    .is_some_and(|(pte_ptr, _)| is_swapped(unsafe { *pte_ptr }))
+                    && !self.populate(vpn.base())
+                {
+                    flush_tlb();
+                    return false;
+                }
+                if let Some((pte_ptr, level)) = self.find_pte(vpn) {
+                    // SAFETY: find_pte 返回本地址空间页表中的有效页表项指针
+                    let pte = unsafe { *pte_ptr };
+                    if pte.is_valid() {
+                        // 大页整体映射到新地址空间，`vpn` 之后属于同一大页的部分一并处理
+                        let pages = level_pages::<Meta>(level);
+                        let offset = vpn.val() & (pages - 1);
+                        count = (pages - offset).min(range.end.val() - vpn.val());
+                        let ppn = pte.ppn() + offset;
+                        let mut flags = pte.flags();
+                        let shared = self
+                            .lazy_areas
+                            .iter()
+                            .any(|area| area.shared && area.range.contains(&vpn));
+                        // 共享映射的页直接共享，仍然可写
+                        if flags.contains(M::WRITABLE) && !shared {
+                            // SAFETY: 只改动 W 和 COW 两个标志位
+                            flags = unsafe {
+                                VmFlags::from_raw((flags.val() & !M::WRITABLE.val()) | M::COW.val())
+                            };
+                            unsafe { *pte_ptr = flags.build_pte(pte.ppn()) };
+                        }
+                        if !new_addrspace.map_pages(vpn..vpn + count, ppn, flags) {
+                            flush_tlb();
+                            return false;
+                        }
+                        // 映射成功后才增加引用，新地址空间释放时正好减回
+                        if self.page_manager.check_owned(pte) {
+                            for i in 0..count {
+                                self.page_manager.share(flags.build_pte(ppn + i));
+                            }
+                        }
+                    }
+                }
+                vpn = vpn + count;
+            }
+        }
+        // 本地址空间的可写页变为只读
+        flush_tlb();
+        true
+    }
+
+    /// 处理对 `addr` 的写入：`addr` 所在页是写时复制页时，复制出独占的物理页并恢复可写，返回 `true`。
+    ///
+    /// 物理页已经只剩本地址空间引用时不再复制，直接恢复可写。没有物理页可供复制时返回 `false`，页保持只读。
+    pub fn copy_on_write(&mut self, addr: VAddr<Meta>) -> bool {
+        // SAFETY: find_pte 返回本地址空间页表中的有效页表项指针
+        let cow = self.find_pte(addr.floor()).is_some_and(|(pte_ptr, _)| {
+            let pte = unsafe { *pte_ptr };
+            pte.is_valid() && pte.flags().contains(M::COW)
+        });
+        if !cow {
+            return false;
+        }
+        // 写时复制的大页只复制被写入的页，没有物理页拆分大页时同样返回 false
+        let Some(pte_ptr) = self.find_pte_mut(addr.floor()) else {
+            return false;
+        };
+        let pte = unsafe { *pte_ptr };
+        let ppn = pte.ppn();
+        // SAFETY: 只改动 W 和 COW 两个标志位
+        let mut flags =
+            unsafe { VmFlags::from_raw((pte.flags().val() & !M::COW.val()) | M::WRITABLE.val()) };
+        let pte = if self.page_manager.is_shared(pte) {
+            let Some(page) = self.page_manager.allocate(1, &mut flags) else {
+                return false;
+            };
+            // SAFETY: 源页和新分配的页都是有效的物理页，两者不重叠
             unsafe {
-                use core::slice::from_raw_parts_mut as slice;
-                let data = slice(data_ptr.as_mut(), size);
-                let ptr = paddr.as_ptr();
-                slice(ptr, size).copy_from_slice(data);
+                core::ptr::copy_nonoverlapping(
+                    self.page_manager.p_to_v::<u8>(ppn).as_ptr(),
+                    page.as_ptr(),
+                    1 << Meta::PAGE_BITS,
+                )
+            };
+            // 原来的页仍被其他地址空间引用，不会释放
+            self.page_manager.deallocate(pte, 1);
+            flags.build_pte(self.page_manager.v_to_p(page))
+        } else {
+            flags.build_pte(ppn)
+        };
+        unsafe { *pte_ptr = pte };
+        flush_tlb();
+        true
+    }
+
+    /// 与 [`Self::translate`] 相同，但先为按需分配的页分配物理页，要求写权限时还会解除写时复制。
+    pub fn translate_mut<T>(
+        &mut self,
+        addr: VAddr<Meta>,
+        flags: VmFlags<Meta>,
+    ) -> Option<NonNull<T>> {
+        self.populate(addr);
+        if flags.contains(M::WRITABLE) {
+            self.copy_on_write(addr);
+            // 内核通过自己的映射写入，硬件不会设置用户页表项的脏位
+            if let Some((pte_ptr, _)) = self.find_pte(addr.floor()) {
+                // SAFETY: find_pte 返回本地址空间页表中的有效页表项指针
+                let pte = unsafe { *pte_ptr };
+                if pte.is_valid() && pte.flags().contains(M::WRITABLE) {
+                    let mut flags = pte.flags();
+                    flags |= M::DIRTY;
+                    unsafe { *pte_ptr = flags.build_pte(pte.ppn()) };
+                }
+            }
+        }
+        self.translate(addr, flags)
+    }
+
+    /// `addr` 是否位于 `shared` 的虚拟地址块中。
+    pub fn is_shared(&self, addr: VAddr<Meta>) -> bool {
+        let vpn = addr.floor();
+        self.lazy_areas
+            .iter()
+            .any(|area| area.shared && area.range.contains(&vpn))
+    }
+}
+
+impl<Meta: VmMeta, M: PageManager<Meta>> AddressSpace<Meta, M> {
+    /// 按 CLOCK 算法换出至多 `count` 个最近没有被访问的页，返回换出的页数。
+    ///
+    /// 只换出私有匿名映射（没有内容来源、不共享的按需分配虚拟地址块）中本地址空间独占的页。
+    /// 时钟指针从上次停下的位置继续：访问位置位的页清除访问位后留到下一轮，否则换出，脏位决定是否需要写入交换区。
+    pub fn reclaim(&mut self, count: usize) -> usize {
+        let mut areas: Vec<Range<VPN<Meta>>> = self
+            .lazy_areas
+            .iter()
+            .filter(|area| !area.shared && area.source.is_none())
+            .filter(|area| <Meta as page_table::MmuMeta>::is_leaf(area.flags.val()))
+            .map(|area| area.range.clone())
+            .collect();
+        if areas.is_empty() {
+            return 0;
+        }
+        areas.sort_unstable_by_key(|area| area.start.val());
+        let total: usize = areas.iter().map(|area| area.end.val() - area.start.val()).sum();
+        let mut i = areas.iter().position(|area| area.end > self.clock).unwrap_or(0);
+        let mut vpn = if areas[i].contains(&self.clock) {
+            self.clock
+        } else {
+            areas[i].start
+        };
+        let mut evicted = 0;
+        // 转两圈：第一圈清除的访问位在第二圈仍未置位的页可以换出
+        let mut scanned = 0;
+        while scanned < 2 * total && evicted < count {
+            if vpn >= areas[i].end {
+                i = (i + 1) % areas.len();
+                vpn = areas[i].start;
+                continue;
+            }
+            if let Some((pte_ptr, 0)) = self.find_pte(vpn) {
+                // SAFETY: find_pte 返回本地址空间页表中的有效页表项指针
+                let pte = unsafe { *pte_ptr };
+                let flags = pte.flags();
+                if pte.is_valid()
+                    && self.page_manager.check_owned(pte)
+                    && !flags.contains(M::COW)
+                    && !self.page_manager.is_shared(pte)
+                {
+                    if flags.contains(M::ACCESSED) {
+                        // SAFETY: 只清除访问位
+                        let flags = unsafe { VmFlags::from_raw(flags.val() & !M::ACCESSED.val()) };
+                        unsafe { *pte_ptr = flags.build_pte(pte.ppn()) };
+                    } else {
+                        let Some(swapped) = self.page_manager.swap_out(pte, flags.contains(M::DIRTY))
+                        else {
+                            // 交换区已满
+                            break;
+                        };
+                        unsafe { *pte_ptr = swapped };
+                        evicted += 1;
+                    }
+                }
             }
-            new_addrspace.map_extern(vpn_range, ppn, flags);
+            vpn = vpn + 1;
+            scanned += 1;
         }
+        self.clock = vpn;
+        flush_tlb();
+        evicted
     }
+
+    /// 释放第 `level` 级页表 `table` 中本地址空间拥有的页和下级页表，不释放 `table` 本身。
+    fn free_table(&mut self, table: NonNull<Pte<Meta>>, level: usize) {
+        for i in 0..level_pages::<Meta>(1) {
+            // SAFETY: table 是本地址空间中有效的页表页，i 不超过页表项数
+            let pte = unsafe { *table.as_ptr().add(i) };
+            if level == 0 && is_swapped(pte) {
+                self.page_manager.swap_free(pte);
+                continue;
+            }
+            // 不属于本地址空间的页（内核映射、异界传送门）不释放
+            if !pte.is_valid() || !self.page_manager.check_owned(pte) {
+                continue;
+            }
+            if level == 0 || <Meta as page_table::MmuMeta>::is_leaf(pte.flags().val()) {
+                // 与其他地址空间共享的页只减少引用
+                self.page_manager.deallocate(pte, level_pages::<Meta>(level));
+            } else {
+                self.free_table(self.page_manager.p_to_v(pte.ppn()), level - 1);
+                self.page_manager.deallocate(pte, 1);
+            }
+        }
+    }
+}
+
+impl<Meta: VmMeta, M: PageManager<Meta>> Drop for AddressSpace<Meta, M> {
+    /// 回收地址空间拥有的所有物理页和页表。
+    fn drop(&mut self) {
+        let root = self.page_manager.root_ptr();
+        self.free_table(root, Meta::MAX_LEVEL);
+        self.page_manager.drop_root();
+    }
+}
+
+/// 页表项中的访问权限位：V、R、W、X、U，与 `VmFlags::build_from_str` 的字符位置一致。
+const PERMISSION_BITS: usize = 0b1_1111;
+/// 用户态不可访问的只读叶子页表项（V、R），用于改为 `PROT_NONE` 的已分配页。
+const NO_ACCESS_BITS: usize = 0b0_0011;
+
+/// 第 `level` 级的一个页表项映射的页数，第 1 级的值也是一个页表中的页表项数。
+#[inline]
+pub(super) fn level_pages<Meta: VmMeta>(level: usize) -> usize {
+    1 << Meta::LEVEL_BITS[..level].iter().sum::<usize>()
+}
+
+/// `pte` 是否指示换出的页：页表项无效，但保留了页属性。
+#[inline]
+fn is_swapped<Meta: VmMeta>(pte: Pte<Meta>) -> bool {
+    !pte.is_valid() && pte.flags().val() != 0
+}
+
+/// 页表改动后刷新 TLB。
+#[inline]
+fn flush_tlb() {
+    #[cfg(target_arch = "riscv64")]
+    unsafe {
+        core::arch::asm!("sfence.vma")
+    };
 }
 
 impl<Meta: VmMeta, P: PageManager<Meta>> fmt::Debug for AddressSpace<Meta, P> {
@@ -1,6 +1,7 @@
-﻿use crate::{AddressSpace, PageManager};
+﻿use super::level_pages;
+use crate::{AddressSpace, PageManager};
 use core::ptr::NonNull;
-use page_table::{Pos, Pte, VmMeta};
+use page_table::{MmuMeta, Pos, Pte, VmMeta};
 
 pub(super) struct Visitor<'a, Meta: VmMeta, M: PageManager<Meta>> {
     space: &'a AddressSpace<Meta, M>,
@@ -31,10 +32,16 @@ impl<'a, Meta: VmMeta, M: PageManager<Meta>> page_table::Visitor<Meta> for Visit
     #[inline]
     fn meet(
         &mut self,
-        _level: usize,
+        level: usize,
         pte: Pte<Meta>,
-        _target_hint: Pos<Meta>,
+        target_hint: Pos<Meta>,
     ) -> Option<NonNull<Pte<Meta>>> {
+        if Meta::is_leaf(pte.flags().val()) {
+            // 大页：换算出目标页在大页中对应的物理页
+            let offset = target_hint.vpn.val() & (level_pages::<Meta>(level) - 1);
+            self.ans = Some(pte.flags().build_pte(pte.ppn() + offset));
+            return None;
+        }
         Some(self.space.page_manager.p_to_v(pte.ppn()))
     }
 
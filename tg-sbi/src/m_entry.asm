@@ -40,6 +40,13 @@ _m_start:
     li t0, -1
     csrw mcounteren, t0
 
+    # No timer until S-Mode asks for one, then enable M-Mode timer interrupt
+    li t0, 0x2004000    # CLINT mtimecmp
+    li t1, -1
+    sd t1, 0(t0)
+    li t0, (1 << 7)     # MTIE
+    csrw mie, t0
+
     # Jump to S-Mode
     mret
 
@@ -47,9 +54,9 @@ _m_start:
     .globl m_trap_vector
     .align 4
 m_trap_vector:
-    # Simple trap handler: handle ecall from S-Mode
+    # Simple trap handler: handle ecall from S-Mode and the machine timer interrupt
     csrrw sp, mscratch, sp
-    addi sp, sp, -128
+    addi sp, sp, -160
 
     # Save registers
     sd ra, 0(sp)
@@ -64,15 +71,27 @@ m_trap_vector:
     sd a5, 72(sp)
     sd a6, 80(sp)
     sd a7, 88(sp)
+    sd t3, 96(sp)
+    sd t4, 104(sp)
+    sd t5, 112(sp)
+    sd t6, 120(sp)
 
     # Call Rust trap handler
     call m_trap_handler
 
+    # Interrupts (mcause < 0) resume the interrupted instruction with a0, a1 intact
+    csrr t0, mcause
+    bltz t0, 1f
+
     # Advance mepc past ecall instruction
     csrr t0, mepc
     addi t0, t0, 4
     csrw mepc, t0
-
+    j 2f
+1:
+    ld a0, 32(sp)
+    ld a1, 40(sp)
+2:
     # Restore registers
     ld ra, 0(sp)
     ld t0, 8(sp)
@@ -85,8 +104,12 @@ m_trap_vector:
     ld a5, 72(sp)
     ld a6, 80(sp)
     ld a7, 88(sp)
+    ld t3, 96(sp)
+    ld t4, 104(sp)
+    ld t5, 112(sp)
+    ld t6, 120(sp)
 
-    addi sp, sp, 128
+    addi sp, sp, 160
     csrrw sp, mscratch, sp
     mret
 
@@ -5,6 +5,8 @@
 //! - 控制台 I/O（UART）
 //! - 定时器管理
 //! - 系统重置
+//!
+//! M-mode 定时器中断无法委托给 S-mode，因此在这里把它转成 S-mode 定时器中断（STIP）。
 
 use core::arch::asm;
 
@@ -135,18 +137,39 @@ fn handle_timer(time: u64) -> SbiRet {
     unsafe {
         (CLINT_MTIMECMP as *mut u64).write_volatile(time);
     }
-    // 清除挂起的 S-mode 定时器中断
-    // SAFETY: 修改 mip CSR 以清除 STIP 位是有效的 M-mode 操作。
+    // 清除挂起的 S-mode 定时器中断，并重新打开 M-mode 定时器中断
+    // SAFETY: 修改 mip 的 STIP 位和 mie 的 MTIE 位是有效的 M-mode 操作。
     // 这是确认定时器中断所必需的。
     unsafe {
         asm!(
             "csrc mip, {}",
             in(reg) (1 << 5), // Clear STIP
         );
+        asm!(
+            "csrs mie, {}",
+            in(reg) (1 << 7), // Set MTIE
+        );
     }
     SbiRet::success(0)
 }
 
+/// 处理 M-mode 定时器中断：转发给 S-mode。
+///
+/// mtimecmp 被重新设置之前 MTIP 会一直挂起，因此先关闭 MTIE，由下一次 `set_timer` 重新打开。
+fn handle_machine_timer() {
+    // SAFETY: 设置 mip 的 STIP 位、清除 mie 的 MTIE 位是有效的 M-mode 操作。
+    unsafe {
+        asm!(
+            "csrs mip, {}",
+            in(reg) (1 << 5), // Set STIP
+        );
+        asm!(
+            "csrc mie, {}",
+            in(reg) (1 << 7), // Clear MTIE
+        );
+    }
+}
+
 /// 处理系统重置请求。
 fn handle_system_reset(fid: usize) -> SbiRet {
     const VIRT_TEST: usize = 0x10_0000;
@@ -187,7 +210,8 @@ fn handle_base(fid: usize) -> SbiRet {
 
 /// 从汇编调用的主 M-mode 陷阱处理程序。
 ///
-/// 此函数处理来自 S-mode 的 ecall，并根据扩展 ID 和功能 ID 分发到相应的处理程序。
+/// 此函数处理来自 S-mode 的 ecall，并根据扩展 ID 和功能 ID 分发到相应的处理程序；
+/// M-mode 定时器中断则转发给 S-mode。
 ///
 /// # Safety
 ///
@@ -212,6 +236,12 @@ pub fn m_trap_handler(
         core::arch::asm!("csrr {}, mcause", out(reg) mcause);
     }
 
+    // M-mode 定时器中断（interrupt = 1, code = 7）
+    if mcause == (1 << (usize::BITS - 1)) | 7 {
+        handle_machine_timer();
+        return SbiRet::success(0);
+    }
+
     if mcause != 9 {
         return SbiRet::not_supported();
     }
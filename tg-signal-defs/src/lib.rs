@@ -4,6 +4,7 @@
 //! - [`SignalNo`] - 信号编号枚举
 //! - [`SignalAction`] - 信号处理函数定义
 //! - [`MAX_SIG`] - 最大信号编号
+//! - [`SIG_DFL`]、[`SIG_IGN`] - 默认处理、忽略信号
 
 #![no_std]
 #![deny(warnings, missing_docs)]
@@ -21,6 +22,11 @@ pub struct SignalAction {
 /// 最大的信号编号
 pub const MAX_SIG: usize = 31;
 
+/// `handler` 取此值时按信号的默认行为处理
+pub const SIG_DFL: usize = 0;
+/// `handler` 取此值时忽略信号
+pub const SIG_IGN: usize = 1;
+
 // 信号标号的定义
 // 目前 rCore-Tutorial 没有用到 [32, 64) 部分的实时信号，但仍给出定义
 numeric_enum_macro::numeric_enum! {
@@ -8,7 +8,7 @@
 extern crate alloc;
 use alloc::boxed::Box;
 use tg_kernel_context::LocalContext;
-use tg_signal::{Signal, SignalAction, SignalNo, SignalResult, MAX_SIG};
+use tg_signal::{Signal, SignalAction, SignalNo, SignalResult, MAX_SIG, SIG_DFL, SIG_IGN};
 
 mod default_action;
 use default_action::DefaultAction;
@@ -91,8 +91,11 @@ impl Signal for SignalImpl {
     }
 
     fn clear(&mut self) {
+        // 与 Linux 相同，被忽略的信号在 exec 后仍然被忽略
         for action in &mut self.actions {
-            action.take();
+            if action.is_some_and(|action| action.handler != SIG_IGN) {
+                action.take();
+            }
         }
     }
 
@@ -154,8 +157,11 @@ impl Signal for SignalImpl {
                     self.handling = Some(HandlingSignal::Frozen);
                     SignalResult::ProcessSuspended
                 }
-                _ => {
-                    if let Some(action) = self.actions[signal as usize] {
+                _ => match self.actions[signal as usize] {
+                    // 显式忽略或恢复默认行为
+                    Some(action) if action.handler == SIG_IGN => SignalResult::Ignored,
+                    Some(action) if action.handler == SIG_DFL => DefaultAction::from(signal).into(),
+                    Some(action) => {
                         // 如果用户给定了处理方式，则按照 SignalAction 中的描述处理
                         // 保存原来用户程序的上下文信息
                         self.handling = Some(HandlingSignal::UserSignal(current_context.clone()));
@@ -164,12 +170,11 @@ impl Signal for SignalImpl {
                         *current_context.pc_mut() = action.handler;
                         *current_context.a_mut(0) = signal as usize;
                         SignalResult::Handled
-                    } else {
-                        // 否则，使用自定义的 DefaultAction 类来处理
-                        // 然后再转换成 SignalResult
-                        DefaultAction::from(signal).into()
                     }
-                }
+                    // 否则，使用自定义的 DefaultAction 类来处理
+                    // 然后再转换成 SignalResult
+                    None => DefaultAction::from(signal).into(),
+                },
             }
         } else {
             SignalResult::NoSignal
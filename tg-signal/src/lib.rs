@@ -9,7 +9,7 @@
 extern crate alloc;
 use alloc::boxed::Box;
 use tg_kernel_context::LocalContext;
-pub use tg_signal_defs::{SignalAction, SignalNo, MAX_SIG};
+pub use tg_signal_defs::{SignalAction, SignalNo, MAX_SIG, SIG_DFL, SIG_IGN};
 
 mod signal_result;
 pub use signal_result::SignalResult;
@@ -21,7 +21,7 @@ pub trait Signal: Send + Sync {
     /// 此时 `task` 模块会调用此函数，根据原任务的信号模块生成新任务的信号模块
     fn from_fork(&mut self) -> Box<dyn Signal>;
 
-    /// `sys_exec`会使用。** `sys_exec` 不会继承信号处理函数和掩码**
+    /// `sys_exec`会使用。** `sys_exec` 不会继承信号处理函数和掩码**，被忽略（`SIG_IGN`）的信号除外
     fn clear(&mut self);
 
     /// 添加一个信号
@@ -1,5 +1,5 @@
 use super::{Mutex, UPIntrFreeCell};
-use alloc::{collections::VecDeque, sync::Arc};
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
 use tg_task_manage::ThreadId;
 
 /// Condvar
@@ -10,8 +10,8 @@ pub struct Condvar {
 
 /// CondvarInner
 pub struct CondvarInner {
-    /// block queue
-    pub wait_queue: VecDeque<ThreadId>,
+    /// block queue，记录等待的线程以及它被唤醒后需要重新获取的互斥锁
+    pub wait_queue: VecDeque<(ThreadId, Arc<dyn Mutex>)>,
 }
 
 impl Condvar {
@@ -27,36 +27,32 @@ impl Condvar {
         }
     }
     /// 唤醒某个阻塞在当前条件变量上的线程
-    pub fn signal(&self) -> Option<ThreadId> {
-        let mut inner = self.inner.exclusive_access();
-        inner.wait_queue.pop_front()
+    ///
+    /// 返回被唤醒的线程和它需要重新竞争的互斥锁，由调用者让它重新获取锁。
+    pub fn signal(&self) -> Option<(ThreadId, Arc<dyn Mutex>)> {
+        self.inner.exclusive_access().wait_queue.pop_front()
     }
-
-    /*
-    pub fn wait(&self) {
-        let mut inner = self.inner.exclusive_access();
-        inner.wait_queue.push_back(current_task().unwrap());
-        drop(inner);
-        block_current_and_run_next();
+    /// 唤醒所有阻塞在当前条件变量上的线程
+    pub fn broadcast(&self) -> Vec<(ThreadId, Arc<dyn Mutex>)> {
+        self.inner.exclusive_access().wait_queue.drain(..).collect()
     }
-    */
-    /// 将当前线程阻塞在条件变量上
-    pub fn wait_no_sched(&self, tid: ThreadId) -> bool {
+    /// 释放 mutex，并将线程阻塞在条件变量的等待队列中
+    ///
+    /// 释放锁和入队之间不会被打断，因此不会错过 signal。
+    /// 返回因锁被释放而获得锁的线程，由调用者唤醒它；当前线程则由调用者阻塞。
+    pub fn wait_with_mutex(&self, tid: ThreadId, mutex: Arc<dyn Mutex>) -> Option<ThreadId> {
+        let waking_tid = mutex.unlock();
         self.inner.exclusive_session(|inner| {
-            inner.wait_queue.push_back(tid);
+            inner.wait_queue.push_back((tid, mutex));
         });
-        false
+        waking_tid
     }
-    /// 从 mutex 的锁中释放一个线程，并将其阻塞在条件变量的等待队列中，等待其他线程运行完毕，当前的线程再试图获取这个锁
+    /// 等待超时，将线程从等待队列中移出
     ///
-    /// 注意：下面是简化版的实现，在 mutex 唤醒一个线程之后，当前线程就直接获取这个 mutex，不管能不能获取成功
-    /// 这里是单纯为了过测例，
-    pub fn wait_with_mutex(
-        &self,
-        tid: ThreadId,
-        mutex: Arc<dyn Mutex>,
-    ) -> (bool, Option<ThreadId>) {
-        let waking_tid = mutex.unlock().unwrap();
-        (mutex.lock(tid), Some(waking_tid))
+    /// 返回它需要重新竞争的互斥锁，线程已经被唤醒时返回 `None`。
+    pub fn cancel_wait(&self, tid: ThreadId) -> Option<Arc<dyn Mutex>> {
+        let mut inner = self.inner.exclusive_access();
+        let idx = inner.wait_queue.iter().position(|(id, _)| *id == tid)?;
+        inner.wait_queue.remove(idx).map(|(_, mutex)| mutex)
     }
 }
@@ -0,0 +1,100 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use tg_task_manage::ThreadId;
+
+/// 死锁检测器
+///
+/// 按银行家算法维护 Available/Allocation/Need，资源下标与内核中同步原语的下标一致。
+/// 互斥锁和信号量各自使用一个检测器，不考虑二者混用导致的死锁。
+#[derive(Default)]
+pub struct DeadlockDetector {
+    /// 可利用资源向量 Available
+    available: Vec<usize>,
+    /// 每个线程的 Allocation 和 Need
+    threads: BTreeMap<ThreadId, DeadlockRow>,
+}
+
+/// 分配矩阵和需求矩阵中某个线程对应的一行
+#[derive(Default)]
+struct DeadlockRow {
+    allocation: Vec<usize>,
+    need: Vec<usize>,
+}
+
+impl DeadlockDetector {
+    /// 创建一个不包含任何资源的检测器。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一类资源，初始可用数量为 `count`，返回资源下标。
+    pub fn add_resource(&mut self, count: usize) -> usize {
+        self.available.push(count);
+        self.available.len() - 1
+    }
+
+    /// 线程 `tid` 申请一个 `res` 资源，记入 Need 后检查系统是否仍处于安全状态。
+    ///
+    /// 不安全时撤销这次申请并返回 false。
+    pub fn request(&mut self, tid: ThreadId, res: usize) -> bool {
+        self.add_need(tid, res);
+        if self.is_safe() {
+            true
+        } else {
+            self.row(tid).need[res] -= 1;
+            false
+        }
+    }
+
+    /// 线程 `tid` 申请一个 `res` 资源，只记入 Need，不做安全性检查（未开启检测或不能拒绝的申请）
+    pub fn add_need(&mut self, tid: ThreadId, res: usize) {
+        self.row(tid).need[res] += 1;
+    }
+
+    /// 线程 `tid` 实际获得了一个 `res` 资源（立即获得或被唤醒后获得）
+    pub fn acquire(&mut self, tid: ThreadId, res: usize) {
+        let row = self.row(tid);
+        row.need[res] = row.need[res].saturating_sub(1);
+        row.allocation[res] += 1;
+        self.available[res] = self.available[res].saturating_sub(1);
+    }
+
+    /// 线程 `tid` 释放一个 `res` 资源
+    ///
+    /// 信号量可以由没有获得过它的线程释放，此时只增加可用数量。
+    pub fn release(&mut self, tid: ThreadId, res: usize) {
+        let row = self.row(tid);
+        row.allocation[res] = row.allocation[res].saturating_sub(1);
+        self.available[res] += 1;
+    }
+
+    /// 线程 `tid` 退出，删除它的一行
+    ///
+    /// 它仍持有的资源不会被释放（例如互斥锁保持锁定），因此也不归还到 Available。
+    pub fn remove_thread(&mut self, tid: ThreadId) {
+        self.threads.remove(&tid);
+    }
+
+    fn row(&mut self, tid: ThreadId) -> &mut DeadlockRow {
+        let m = self.available.len();
+        let row = self.threads.entry(tid).or_default();
+        row.allocation.resize(m, 0);
+        row.need.resize(m, 0);
+        row
+    }
+
+    /// 安全性检查：能否找到一个序列让所有线程依次获得所需资源并运行结束
+    fn is_safe(&self) -> bool {
+        let mut work = self.available.clone();
+        let rows: Vec<&DeadlockRow> = self.threads.values().collect();
+        let mut finish = vec![false; rows.len()];
+        while let Some(i) = (0..rows.len()).find(|&i| {
+            !finish[i] && rows[i].need.iter().zip(&work).all(|(need, work)| need <= work)
+        }) {
+            for (work, allocation) in work.iter_mut().zip(&rows[i].allocation) {
+                *work += allocation;
+            }
+            finish[i] = true;
+        }
+        finish.iter().all(|&finished| finished)
+    }
+}
@@ -4,6 +4,7 @@
 #![deny(warnings, missing_docs)]
 
 mod condvar;
+mod deadlock;
 mod mutex;
 mod semaphore;
 mod up;
@@ -11,6 +12,7 @@ mod up;
 extern crate alloc;
 
 pub use condvar::Condvar;
+pub use deadlock::DeadlockDetector;
 pub use mutex::{Mutex, MutexBlocking};
 pub use semaphore::Semaphore;
 pub use up::{UPIntrFreeCell, UPIntrRefMut};
@@ -8,6 +8,10 @@ pub trait Mutex: Sync + Send {
     fn lock(&self, tid: ThreadId) -> bool;
     /// 当前线程释放锁，并唤醒某个阻塞在这个锁上的线程
     fn unlock(&self) -> Option<ThreadId>;
+    /// 持有锁的线程，未锁定时返回 `None`
+    fn owner(&self) -> Option<ThreadId>;
+    /// 线程放弃等待，从等待队列中移出，不在等待队列中时返回 false
+    fn cancel_wait(&self, tid: ThreadId) -> bool;
 }
 
 /// MutexBlocking
@@ -17,7 +21,8 @@ pub struct MutexBlocking {
 
 /// MutexBlockingInner
 pub struct MutexBlockingInner {
-    locked: bool,
+    /// 持有锁的线程，未锁定时为 `None`
+    owner: Option<ThreadId>,
     wait_queue: VecDeque<ThreadId>,
 }
 
@@ -28,7 +33,7 @@ impl MutexBlocking {
             // SAFETY: 此互斥锁仅在单处理器内核环境中使用
             inner: unsafe {
                 UPIntrFreeCell::new(MutexBlockingInner {
-                    locked: false,
+                    owner: None,
                     wait_queue: VecDeque::new(),
                 })
             },
@@ -40,24 +45,32 @@ impl Mutex for MutexBlocking {
     // 获取锁，如果获取成功，返回 true，否则会返回 false，要求阻塞对应的线程
     fn lock(&self, tid: ThreadId) -> bool {
         let mut mutex_inner = self.inner.exclusive_access();
-        if mutex_inner.locked {
+        if mutex_inner.owner.is_some() {
             mutex_inner.wait_queue.push_back(tid);
             drop(mutex_inner);
             false
         } else {
-            mutex_inner.locked = true;
+            mutex_inner.owner = Some(tid);
             true
         }
     }
     // 释放锁，释放之后会唤醒一个被阻塞的进程，要求重新进入调度队列
+    // 锁直接交给被唤醒的线程；调用者须先通过 `owner` 确认当前线程持有锁
     fn unlock(&self) -> Option<ThreadId> {
         let mut mutex_inner = self.inner.exclusive_access();
-        assert!(mutex_inner.locked);
-        if let Some(waking_task) = mutex_inner.wait_queue.pop_front() {
-            Some(waking_task)
-        } else {
-            mutex_inner.locked = false;
-            None
-        }
+        assert!(mutex_inner.owner.is_some());
+        mutex_inner.owner = mutex_inner.wait_queue.pop_front();
+        mutex_inner.owner
+    }
+    fn owner(&self) -> Option<ThreadId> {
+        self.inner.exclusive_access().owner
+    }
+    fn cancel_wait(&self, tid: ThreadId) -> bool {
+        let mut mutex_inner = self.inner.exclusive_access();
+        let Some(idx) = mutex_inner.wait_queue.iter().position(|&id| id == tid) else {
+            return false;
+        };
+        mutex_inner.wait_queue.remove(idx);
+        true
     }
 }
@@ -45,4 +45,14 @@ impl Semaphore {
             true
         }
     }
+    /// 线程放弃等待，从等待队列中移出并归还它预占的计数，不在等待队列中时返回 false
+    pub fn cancel_wait(&self, tid: ThreadId) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        let Some(idx) = inner.wait_queue.iter().position(|&id| id == tid) else {
+            return false;
+        };
+        inner.wait_queue.remove(idx);
+        inner.count += 1;
+        true
+    }
 }
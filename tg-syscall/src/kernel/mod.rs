@@ -24,24 +24,75 @@ pub trait Process: Sync {
     fn fork(&self, caller: Caller) -> isize {
         unimplemented!()
     }
-    fn exec(&self, caller: Caller, path: usize, count: usize) -> isize {
+    /// Linux `clone`，参数顺序为 riscv64 上的 `(flags, stack, ptid, tls, ctid)`。
+    fn clone(
+        &self,
+        caller: Caller,
+        flags: usize,
+        stack: usize,
+        ptid: usize,
+        tls: usize,
+        ctid: usize,
+    ) -> isize {
         unimplemented!()
     }
-    fn wait(&self, caller: Caller, pid: isize, exit_code_ptr: usize) -> isize {
+    fn execve(&self, caller: Caller, path: usize, argv: usize, envp: usize) -> isize {
+        unimplemented!()
+    }
+    fn wait4(
+        &self,
+        caller: Caller,
+        pid: isize,
+        wstatus: usize,
+        options: u32,
+        rusage: usize,
+    ) -> isize {
         unimplemented!()
     }
     fn getpid(&self, caller: Caller) -> isize {
         unimplemented!()
     }
+    fn getppid(&self, caller: Caller) -> isize {
+        unimplemented!()
+    }
+    fn setpgid(&self, caller: Caller, pid: isize, pgid: isize) -> isize {
+        unimplemented!()
+    }
+    fn getpgid(&self, caller: Caller, pid: isize) -> isize {
+        unimplemented!()
+    }
+    fn setsid(&self, caller: Caller) -> isize {
+        unimplemented!()
+    }
+    fn getsid(&self, caller: Caller, pid: isize) -> isize {
+        unimplemented!()
+    }
     fn set_tid_address(&self, caller: Caller, tidp: usize) -> isize {
         unimplemented!()
     }
     fn set_robust_list(&self, caller: Caller, head: usize, len: usize) -> isize {
         unimplemented!()
     }
+    /// Linux `futex`，`timeout` 在 REQUEUE 类操作中表示 `val2`。
+    #[allow(clippy::too_many_arguments)]
+    fn futex(
+        &self,
+        caller: Caller,
+        uaddr: usize,
+        op: u32,
+        val: u32,
+        timeout: usize,
+        uaddr2: usize,
+        val3: u32,
+    ) -> isize {
+        unimplemented!()
+    }
     fn prlimit64(&self, caller: Caller, pid: isize, resource: u32, new_limit: usize, old_limit: usize) -> isize {
         unimplemented!()
     }
+    fn prctl(&self, caller: Caller, option: u32, arg2: usize, arg3: usize, arg4: usize, arg5: usize) -> isize {
+        unimplemented!()
+    }
 }
 
 pub trait IO: Sync {
@@ -117,6 +168,10 @@ pub trait Memory: Sync {
     fn munmap(&self, caller: Caller, addr: usize, length: usize) -> isize {
         unimplemented!()
     }
+
+    fn msync(&self, caller: Caller, addr: usize, length: usize, flags: u32) -> isize {
+        unimplemented!()
+    }
 }
 
 pub trait Scheduling: Sync {
@@ -126,12 +181,49 @@ pub trait Scheduling: Sync {
     fn nanosleep(&self, caller: Caller, req: usize, rem: usize) -> isize {
         unimplemented!()
     }
+    fn sched_setparam(&self, caller: Caller, pid: isize, param: usize) -> isize {
+        unimplemented!()
+    }
+    fn sched_setscheduler(&self, caller: Caller, pid: isize, policy: isize, param: usize) -> isize {
+        unimplemented!()
+    }
+    fn sched_getscheduler(&self, caller: Caller, pid: isize) -> isize {
+        unimplemented!()
+    }
+    fn sched_getparam(&self, caller: Caller, pid: isize, param: usize) -> isize {
+        unimplemented!()
+    }
+    fn setpriority(&self, caller: Caller, which: isize, who: isize, prio: isize) -> isize {
+        unimplemented!()
+    }
+    fn getpriority(&self, caller: Caller, which: isize, who: isize) -> isize {
+        unimplemented!()
+    }
 }
 
 pub trait Clock: Sync {
     fn clock_gettime(&self, caller: Caller, clock_id: ClockId, tp: usize) -> isize {
         unimplemented!()
     }
+    fn clock_getres(&self, caller: Caller, clock_id: ClockId, res: usize) -> isize {
+        unimplemented!()
+    }
+    fn gettimeofday(&self, caller: Caller, tv: usize, tz: usize) -> isize {
+        unimplemented!()
+    }
+    fn times(&self, caller: Caller, buf: usize) -> isize {
+        unimplemented!()
+    }
+    fn clock_nanosleep(
+        &self,
+        caller: Caller,
+        clock_id: ClockId,
+        flags: usize,
+        req: usize,
+        rem: usize,
+    ) -> isize {
+        unimplemented!()
+    }
 }
 
 pub trait Signal: Sync {
@@ -156,12 +248,66 @@ pub trait Signal: Sync {
     }
 }
 
+pub trait Thread: Sync {
+    fn thread_create(&self, caller: Caller, entry: usize, arg: usize) -> isize {
+        unimplemented!()
+    }
+    fn gettid(&self, caller: Caller) -> isize {
+        unimplemented!()
+    }
+    fn waittid(&self, caller: Caller, tid: usize) -> isize {
+        unimplemented!()
+    }
+}
+
+pub trait SyncMutex: Sync {
+    fn semaphore_create(&self, caller: Caller, res_count: usize) -> isize {
+        unimplemented!()
+    }
+    fn semaphore_up(&self, caller: Caller, sem_id: usize) -> isize {
+        unimplemented!()
+    }
+    fn semaphore_down(&self, caller: Caller, sem_id: usize) -> isize {
+        unimplemented!()
+    }
+    fn mutex_create(&self, caller: Caller, blocking: bool) -> isize {
+        unimplemented!()
+    }
+    fn mutex_unlock(&self, caller: Caller, mutex_id: usize) -> isize {
+        unimplemented!()
+    }
+    fn mutex_lock(&self, caller: Caller, mutex_id: usize) -> isize {
+        unimplemented!()
+    }
+    fn condvar_create(&self, caller: Caller, arg: usize) -> isize {
+        unimplemented!()
+    }
+    fn condvar_signal(&self, caller: Caller, condvar_id: usize) -> isize {
+        unimplemented!()
+    }
+    fn condvar_broadcast(&self, caller: Caller, condvar_id: usize) -> isize {
+        unimplemented!()
+    }
+    fn condvar_wait(&self, caller: Caller, condvar_id: usize, mutex_id: usize) -> isize {
+        unimplemented!()
+    }
+    /// `abstime` 指向单调时钟上的绝对超时时刻（`TimeSpec`）。
+    fn condvar_timedwait(&self, caller: Caller, condvar_id: usize, mutex_id: usize, abstime: usize) -> isize {
+        unimplemented!()
+    }
+    fn enable_deadlock_detect(&self, caller: Caller, is_enable: i32) -> isize {
+        unimplemented!()
+    }
+}
+
 static PROCESS: Container<dyn Process> = Container::new();
 static IO: Container<dyn IO> = Container::new();
 static MEMORY: Container<dyn Memory> = Container::new();
 static SCHEDULING: Container<dyn Scheduling> = Container::new();
 static CLOCK: Container<dyn Clock> = Container::new();
 static SIGNAL: Container<dyn Signal> = Container::new();
+static THREAD: Container<dyn Thread> = Container::new();
+static SYNC_MUTEX: Container<dyn SyncMutex> = Container::new();
 
 #[inline]
 pub fn init_process(process: &'static dyn Process) {
@@ -193,6 +339,16 @@ pub fn init_signal(signal: &'static dyn Signal) {
     SIGNAL.init(signal);
 }
 
+#[inline]
+pub fn init_thread(thread: &'static dyn Thread) {
+    THREAD.init(thread);
+}
+
+#[inline]
+pub fn init_sync_mutex(sync: &'static dyn SyncMutex) {
+    SYNC_MUTEX.init(sync);
+}
+
 pub enum SyscallResult {
     Done(isize),
     Unsupported(SyscallId),
@@ -229,17 +385,55 @@ pub fn handle(caller: Caller, id: SyscallId, args: [usize; 6]) -> SyscallResult
         Id::FSTAT => IO.call(id, |io| io.fstat(caller, args[0], args[1])),
         Id::EXIT => PROCESS.call(id, |proc| proc.exit(caller, args[0])),
         Id::EXIT_GROUP => PROCESS.call(id, |proc| proc.exit_group(caller, args[0])),
-        Id::CLONE => PROCESS.call(id, |proc| proc.fork(caller)),
-        Id::EXECVE => PROCESS.call(id, |proc| proc.exec(caller, args[0], args[1])),
-        Id::WAIT4 => PROCESS.call(id, |proc| proc.wait(caller, args[0] as _, args[1])),
+        Id::CLONE => PROCESS.call(id, |proc| {
+            proc.clone(caller, args[0], args[1], args[2], args[3], args[4])
+        }),
+        Id::EXECVE => PROCESS.call(id, |proc| proc.execve(caller, args[0], args[1], args[2])),
+        Id::WAIT4 => PROCESS.call(id, |proc| {
+            proc.wait4(caller, args[0] as _, args[1], args[2] as _, args[3])
+        }),
         Id::GETPID => PROCESS.call(id, |proc| proc.getpid(caller)),
+        Id::GETPPID => PROCESS.call(id, |proc| proc.getppid(caller)),
+        Id::SETPGID => PROCESS.call(id, |proc| proc.setpgid(caller, args[0] as _, args[1] as _)),
+        Id::GETPGID => PROCESS.call(id, |proc| proc.getpgid(caller, args[0] as _)),
+        Id::SETSID => PROCESS.call(id, |proc| proc.setsid(caller)),
+        Id::GETSID => PROCESS.call(id, |proc| proc.getsid(caller, args[0] as _)),
         Id::SET_TID_ADDRESS => PROCESS.call(id, |proc| proc.set_tid_address(caller, args[0])),
         Id::SET_ROBUST_LIST => PROCESS.call(id, |proc| proc.set_robust_list(caller, args[0], args[1])),
+        Id::FUTEX => PROCESS.call(id, |proc| {
+            proc.futex(caller, args[0], args[1] as _, args[2] as _, args[3], args[4], args[5] as _)
+        }),
         Id::CLOCK_GETTIME => CLOCK.call(id, |clock| {
             clock.clock_gettime(caller, ClockId(args[0]), args[1])
         }),
+        Id::CLOCK_GETRES => CLOCK.call(id, |clock| {
+            clock.clock_getres(caller, ClockId(args[0]), args[1])
+        }),
+        Id::GETTIMEOFDAY => CLOCK.call(id, |clock| clock.gettimeofday(caller, args[0], args[1])),
+        Id::TIMES => CLOCK.call(id, |clock| clock.times(caller, args[0])),
+        Id::CLOCK_NANOSLEEP => CLOCK.call(id, |clock| {
+            clock.clock_nanosleep(caller, ClockId(args[0]), args[1], args[2], args[3])
+        }),
         Id::SCHED_YIELD => SCHEDULING.call(id, |sched| sched.sched_yield(caller)),
         Id::NANOSLEEP => SCHEDULING.call(id, |sched| sched.nanosleep(caller, args[0], args[1])),
+        Id::SCHED_SETPARAM => SCHEDULING.call(id, |sched| {
+            sched.sched_setparam(caller, args[0] as _, args[1])
+        }),
+        Id::SCHED_SETSCHEDULER => SCHEDULING.call(id, |sched| {
+            sched.sched_setscheduler(caller, args[0] as _, args[1] as _, args[2])
+        }),
+        Id::SCHED_GETSCHEDULER => SCHEDULING.call(id, |sched| {
+            sched.sched_getscheduler(caller, args[0] as _)
+        }),
+        Id::SCHED_GETPARAM => SCHEDULING.call(id, |sched| {
+            sched.sched_getparam(caller, args[0] as _, args[1])
+        }),
+        Id::SETPRIORITY => SCHEDULING.call(id, |sched| {
+            sched.setpriority(caller, args[0] as _, args[1] as _, args[2] as _)
+        }),
+        Id::GETPRIORITY => SCHEDULING.call(id, |sched| {
+            sched.getpriority(caller, args[0] as _, args[1] as _)
+        }),
         Id::BRK => MEMORY.call(id, |memory| memory.brk(caller, args[0])),
         Id::GETRANDOM => MEMORY.call(id, |memory| {
             memory.getrandom(caller, args[0], args[1], args[2] as _)
@@ -248,6 +442,9 @@ pub fn handle(caller: Caller, id: SyscallId, args: [usize; 6]) -> SyscallResult
             memory.mprotect(caller, args[0], args[1], args[2] as _)
         }),
         Id::MUNMAP => MEMORY.call(id, |memory| memory.munmap(caller, args[0], args[1])),
+        Id::MSYNC => MEMORY.call(id, |memory| {
+            memory.msync(caller, args[0], args[1], args[2] as _)
+        }),
         Id::MMAP => MEMORY.call(id, |memory| {
             let [addr, length, prot, flags, fd, offset] = args;
             memory.mmap(caller, addr, length, prot as _, flags as _, fd as _, offset)
@@ -263,6 +460,28 @@ pub fn handle(caller: Caller, id: SyscallId, args: [usize; 6]) -> SyscallResult
         Id::PRLIMIT64 => PROCESS.call(id, |proc| {
             proc.prlimit64(caller, args[0] as _, args[1] as _, args[2], args[3])
         }),
+        Id::PRCTL => PROCESS.call(id, |proc| {
+            proc.prctl(caller, args[0] as _, args[1], args[2], args[3], args[4])
+        }),
+        Id::THREAD_CREATE => THREAD.call(id, |thread| thread.thread_create(caller, args[0], args[1])),
+        Id::GETTID => THREAD.call(id, |thread| thread.gettid(caller)),
+        Id::WAITTID => THREAD.call(id, |thread| thread.waittid(caller, args[0])),
+        Id::SEMAPHORE_CREATE => SYNC_MUTEX.call(id, |sync| sync.semaphore_create(caller, args[0])),
+        Id::SEMAPHORE_UP => SYNC_MUTEX.call(id, |sync| sync.semaphore_up(caller, args[0])),
+        Id::SEMAPHORE_DOWN => SYNC_MUTEX.call(id, |sync| sync.semaphore_down(caller, args[0])),
+        Id::MUTEX_CREATE => SYNC_MUTEX.call(id, |sync| sync.mutex_create(caller, args[0] != 0)),
+        Id::MUTEX_LOCK => SYNC_MUTEX.call(id, |sync| sync.mutex_lock(caller, args[0])),
+        Id::MUTEX_UNLOCK => SYNC_MUTEX.call(id, |sync| sync.mutex_unlock(caller, args[0])),
+        Id::CONDVAR_CREATE => SYNC_MUTEX.call(id, |sync| sync.condvar_create(caller, args[0])),
+        Id::CONDVAR_SIGNAL => SYNC_MUTEX.call(id, |sync| sync.condvar_signal(caller, args[0])),
+        Id::CONDVAR_BROADCAST => SYNC_MUTEX.call(id, |sync| sync.condvar_broadcast(caller, args[0])),
+        Id::CONDVAR_WAIT => SYNC_MUTEX.call(id, |sync| sync.condvar_wait(caller, args[0], args[1])),
+        Id::CONDVAR_TIMEDWAIT => SYNC_MUTEX.call(id, |sync| {
+            sync.condvar_timedwait(caller, args[0], args[1], args[2])
+        }),
+        Id::ENABLE_DEADLOCK_DETECT => SYNC_MUTEX.call(id, |sync| {
+            sync.enable_deadlock_detect(caller, args[0] as _)
+        }),
         _ => SyscallResult::Unsupported(id),
     }
 }
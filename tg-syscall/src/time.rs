@@ -55,6 +55,12 @@ impl TimeSpec {
             tv_nsec: millsecond % 1_000 * 1_000_000,
         }
     }
+    pub fn from_nanosecond(nanosecond: usize) -> Self {
+        Self {
+            tv_sec: nanosecond / 1_000_000_000,
+            tv_nsec: nanosecond % 1_000_000_000,
+        }
+    }
 }
 
 impl core::ops::Add<TimeSpec> for TimeSpec {
@@ -77,3 +83,37 @@ impl core::fmt::Display for TimeSpec {
         write!(f, "TimeSpec({}.{:09})", self.tv_sec, self.tv_nsec)
     }
 }
+
+/// `gettimeofday` 使用的时间。
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[repr(C)]
+pub struct TimeVal {
+    // seconds
+    pub tv_sec: usize,
+    // microseconds
+    pub tv_usec: usize,
+}
+
+impl TimeVal {
+    pub fn from_nanosecond(nanosecond: usize) -> Self {
+        Self {
+            tv_sec: nanosecond / 1_000_000_000,
+            tv_usec: nanosecond % 1_000_000_000 / 1_000,
+        }
+    }
+}
+
+/// `times` 返回的进程时间，单位为时钟滴答（[`Tms::CLK_TCK`]）。
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[repr(C)]
+pub struct Tms {
+    pub tms_utime: isize,
+    pub tms_stime: isize,
+    pub tms_cutime: isize,
+    pub tms_cstime: isize,
+}
+
+impl Tms {
+    /// 每秒的时钟滴答数，与 Linux 的 `USER_HZ` 相同
+    pub const CLK_TCK: usize = 100;
+}
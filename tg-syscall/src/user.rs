@@ -1,4 +1,4 @@
-use crate::{ClockId, SignalAction, SignalNo, Stat, SyscallId, TimeSpec};
+use crate::{ClockId, SignalAction, SignalNo, Stat, SyscallId, TimeSpec, TimeVal, Tms};
 use bitflags::*;
 use native::*;
 
@@ -102,6 +102,78 @@ pub fn sched_yield() -> isize {
     unsafe { syscall0(SyscallId::SCHED_YIELD) }
 }
 
+/// 设置线程的调度策略和实时优先级，`pid` 为线程 ID，0 表示当前线程。
+///
+/// `priority` 即 `struct sched_param` 唯一的成员 `sched_priority`。
+///
+/// see <https://man7.org/linux/man-pages/man2/sched_setscheduler.2.html>.
+#[inline]
+pub fn sched_setscheduler(pid: usize, policy: usize, priority: &i32) -> isize {
+    // SAFETY: priority 是有效的引用
+    unsafe {
+        syscall3(
+            SyscallId::SCHED_SETSCHEDULER,
+            pid,
+            policy,
+            priority as *const _ as usize,
+        )
+    }
+}
+
+/// 获取线程的调度策略。
+///
+/// see <https://man7.org/linux/man-pages/man2/sched_getscheduler.2.html>.
+#[inline]
+pub fn sched_getscheduler(pid: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::SCHED_GETSCHEDULER, pid) }
+}
+
+/// 设置线程的实时优先级，不改变调度策略。
+///
+/// see <https://man7.org/linux/man-pages/man2/sched_setparam.2.html>.
+#[inline]
+pub fn sched_setparam(pid: usize, priority: &i32) -> isize {
+    // SAFETY: priority 是有效的引用
+    unsafe { syscall2(SyscallId::SCHED_SETPARAM, pid, priority as *const _ as usize) }
+}
+
+/// 获取线程的实时优先级。
+///
+/// see <https://man7.org/linux/man-pages/man2/sched_getparam.2.html>.
+#[inline]
+pub fn sched_getparam(pid: usize, priority: &mut i32) -> isize {
+    // SAFETY: priority 是有效的可变引用
+    unsafe { syscall2(SyscallId::SCHED_GETPARAM, pid, priority as *mut _ as usize) }
+}
+
+/// 设置 nice 值。
+///
+/// see <https://man7.org/linux/man-pages/man2/setpriority.2.html>.
+#[inline]
+pub fn setpriority(which: usize, who: usize, prio: isize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall3(SyscallId::SETPRIORITY, which, who, prio as _) }
+}
+
+/// 进程控制，目前支持 `PR_SET_CHILD_SUBREAPER`/`PR_GET_CHILD_SUBREAPER`。
+///
+/// see <https://man7.org/linux/man-pages/man2/prctl.2.html>.
+#[inline]
+pub fn prctl(option: u32, arg2: usize) -> isize {
+    // SAFETY: PR_GET_CHILD_SUBREAPER 时调用者需要确保 arg2 指向有效的可写 int
+    unsafe { syscall5(SyscallId::PRCTL, option as _, arg2, 0, 0, 0) }
+}
+
+/// 获取 nice 值，与 Linux 系统调用相同，返回 `20 - nice`。
+///
+/// see <https://man7.org/linux/man-pages/man2/getpriority.2.html>.
+#[inline]
+pub fn getpriority(which: usize, who: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall2(SyscallId::GETPRIORITY, which, who) }
+}
+
 /// 获取时钟时间。
 ///
 /// see <https://man7.org/linux/man-pages/man2/clock_gettime.2.html>.
@@ -111,42 +183,118 @@ pub fn clock_gettime(clockid: ClockId, tp: *mut TimeSpec) -> isize {
     unsafe { syscall2(SyscallId::CLOCK_GETTIME, clockid.0, tp as _) }
 }
 
+/// 获取时钟精度。
+///
+/// see <https://man7.org/linux/man-pages/man2/clock_getres.2.html>.
+#[inline]
+pub fn clock_getres(clockid: ClockId, res: *mut TimeSpec) -> isize {
+    // SAFETY: 调用者需要确保 res 为空或指向有效的可写内存
+    unsafe { syscall2(SyscallId::CLOCK_GETRES, clockid.0, res as _) }
+}
+
+/// 获取墙上时间，不支持时区。
+///
+/// see <https://man7.org/linux/man-pages/man2/gettimeofday.2.html>.
+#[inline]
+pub fn gettimeofday(tv: &mut TimeVal) -> isize {
+    // SAFETY: tv 是有效的可变引用
+    unsafe { syscall2(SyscallId::GETTIMEOFDAY, tv as *mut _ as usize, 0) }
+}
+
+/// 获取进程时间，返回自启动以来的时钟滴答数。
+///
+/// see <https://man7.org/linux/man-pages/man2/times.2.html>.
+#[inline]
+pub fn times(buf: &mut Tms) -> isize {
+    // SAFETY: buf 是有效的可变引用
+    unsafe { syscall1(SyscallId::TIMES, buf as *mut _ as usize) }
+}
+
+/// 睡眠 `req` 指定的时间，被信号打断时把剩余时间写入 `rem`。
+///
+/// see <https://man7.org/linux/man-pages/man2/nanosleep.2.html>.
+#[inline]
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    let rem = rem.map_or(0, |rem| rem as *mut _ as usize);
+    // SAFETY: req 是有效的引用，rem 为空或有效的可变引用
+    unsafe { syscall2(SyscallId::NANOSLEEP, req as *const _ as usize, rem) }
+}
+
+/// 按指定时钟睡眠，`flags` 为 `TIMER_ABSTIME`（1）时 `req` 是绝对时刻。
+///
+/// see <https://man7.org/linux/man-pages/man2/clock_nanosleep.2.html>.
+#[inline]
+pub fn clock_nanosleep(
+    clockid: ClockId,
+    flags: usize,
+    req: &TimeSpec,
+    rem: Option<&mut TimeSpec>,
+) -> isize {
+    let rem = rem.map_or(0, |rem| rem as *mut _ as usize);
+    // SAFETY: req 是有效的引用，rem 为空或有效的可变引用
+    unsafe {
+        syscall4(
+            SyscallId::CLOCK_NANOSLEEP,
+            clockid.0,
+            flags,
+            req as *const _ as usize,
+            rem,
+        )
+    }
+}
+
 /// 创建子进程。
+///
+/// 与 Linux 一致，`fork` 即 `clone(SIGCHLD, 0)`。
 pub fn fork() -> isize {
-    // SAFETY: 无参数系统调用
-    unsafe { syscall0(SyscallId::CLONE) }
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall2(SyscallId::CLONE, SignalNo::SIGCHLD as _, 0) }
 }
 
-/// 执行新程序。
+/// 执行新程序，`path` 须以 `\0` 结尾，参数只有程序名，没有环境变量。
 pub fn exec(path: &str) -> isize {
-    // SAFETY: path 是有效的字符串引用
-    unsafe { syscall2(SyscallId::EXECVE, path.as_ptr() as usize, path.len()) }
+    execve(path, &[path.as_ptr(), core::ptr::null()], &[core::ptr::null()])
+}
+
+/// 执行新程序，`argv`、`envp` 为以空指针结尾的字符串指针数组。
+///
+/// see <https://man7.org/linux/man-pages/man2/execve.2.html>.
+pub fn execve(path: &str, argv: &[*const u8], envp: &[*const u8]) -> isize {
+    // SAFETY: 调用者需要确保 path 和 argv、envp 指向的字符串以 `\0` 结尾，两个数组以空指针结尾
+    unsafe {
+        syscall3(
+            SyscallId::EXECVE,
+            path.as_ptr() as usize,
+            argv.as_ptr() as usize,
+            envp.as_ptr() as usize,
+        )
+    }
+}
+
+/// 等待子进程的状态变化，`pid` 的含义与 Linux 相同，`wstatus` 按 `WIFEXITED` 等宏的格式编码。
+///
+/// see <https://man7.org/linux/man-pages/man2/wait4.2.html>.
+pub fn wait4(pid: isize, wstatus: *mut i32, options: u32, rusage: usize) -> isize {
+    // SAFETY: 调用者需要确保 wstatus 和 rusage 为空或指向有效的可写内存
+    unsafe {
+        syscall4(
+            SyscallId::WAIT4,
+            pid as usize,
+            wstatus as usize,
+            options as usize,
+            rusage,
+        )
+    }
 }
 
 /// 等待任意子进程退出。
 pub fn wait(exit_code_ptr: *mut i32) -> isize {
-    loop {
-        // SAFETY: 调用者需要确保 exit_code_ptr 指向有效的可写内存（或为 null）
-        match unsafe { syscall2(SyscallId::WAIT4, usize::MAX, exit_code_ptr as usize) } {
-            -2 => {
-                sched_yield();
-            }
-            exit_pid => return exit_pid,
-        }
-    }
+    wait4(-1, exit_code_ptr, 0, 0)
 }
 
 /// 等待指定子进程退出。
 pub fn waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    loop {
-        // SAFETY: 调用者需要确保 exit_code_ptr 指向有效的可写内存（或为 null）
-        match unsafe { syscall2(SyscallId::WAIT4, pid as usize, exit_code_ptr as usize) } {
-            -2 => {
-                sched_yield();
-            }
-            exit_pid => return exit_pid,
-        }
-    }
+    wait4(pid, exit_code_ptr, 0, 0)
 }
 
 /// 获取当前进程 ID。
@@ -155,6 +303,43 @@ pub fn getpid() -> isize {
     unsafe { syscall0(SyscallId::GETPID) }
 }
 
+/// 获取父进程 ID，没有父进程时返回 0。
+pub fn getppid() -> isize {
+    // SAFETY: 无参数系统调用
+    unsafe { syscall0(SyscallId::GETPPID) }
+}
+
+/// 把进程 `pid`（0 表示当前进程）移入进程组 `pgid`（0 表示与 `pid` 相同）。
+///
+/// see <https://man7.org/linux/man-pages/man2/setpgid.2.html>.
+pub fn setpgid(pid: isize, pgid: isize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall2(SyscallId::SETPGID, pid as _, pgid as _) }
+}
+
+/// 获取进程 `pid`（0 表示当前进程）所在的进程组。
+pub fn getpgid(pid: isize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::GETPGID, pid as _) }
+}
+
+/// 获取当前进程所在的进程组，RISC-V 上没有单独的系统调用，同 `getpgid(0)`。
+pub fn getpgrp() -> isize {
+    getpgid(0)
+}
+
+/// 新建一个会话，返回会话 ID。
+pub fn setsid() -> isize {
+    // SAFETY: 无参数系统调用
+    unsafe { syscall0(SyscallId::SETSID) }
+}
+
+/// 获取进程 `pid`（0 表示当前进程）所在的会话。
+pub fn getsid(pid: isize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::GETSID, pid as _) }
+}
+
 /// 创建并运行一个新进程。
 
 /// 向进程发送信号。
@@ -211,12 +396,137 @@ pub fn munmap(start: usize, len: usize) -> isize {
     unsafe { syscall2(SyscallId::MUNMAP, start, len) }
 }
 
+/// 把共享文件映射的修改写回文件。
+#[inline]
+pub fn msync(start: usize, len: usize, flags: u32) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall3(SyscallId::MSYNC, start, len, flags as _) }
+}
+
 /// 创建管道
 #[inline]
 pub fn pipe(pipe_fd: &mut [usize]) -> isize {
     unsafe { syscall1(SyscallId::PIPE2, pipe_fd.as_mut_ptr() as _) }
 }
 
+/// 创建线程，新线程从 `entry` 开始执行，`arg` 作为第一个参数。
+#[inline]
+pub fn thread_create(entry: usize, arg: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall2(SyscallId::THREAD_CREATE, entry, arg) }
+}
+
+/// 获取当前线程 ID。
+#[inline]
+pub fn gettid() -> isize {
+    // SAFETY: 无参数系统调用
+    unsafe { syscall0(SyscallId::GETTID) }
+}
+
+/// 等待同一进程中的线程退出，返回其退出码。
+pub fn waittid(tid: usize) -> isize {
+    loop {
+        // SAFETY: 系统调用参数是简单的整数值
+        match unsafe { syscall1(SyscallId::WAITTID, tid) } {
+            -2 => {
+                sched_yield();
+            }
+            exit_code => return exit_code,
+        }
+    }
+}
+
+/// 创建互斥锁，返回锁的 ID。
+#[inline]
+pub fn mutex_create(blocking: bool) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::MUTEX_CREATE, blocking as _) }
+}
+
+/// 加锁。
+#[inline]
+pub fn mutex_lock(mutex_id: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::MUTEX_LOCK, mutex_id) }
+}
+
+/// 解锁。
+#[inline]
+pub fn mutex_unlock(mutex_id: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::MUTEX_UNLOCK, mutex_id) }
+}
+
+/// 创建信号量，返回信号量的 ID。
+#[inline]
+pub fn semaphore_create(res_count: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::SEMAPHORE_CREATE, res_count) }
+}
+
+/// V 操作。
+#[inline]
+pub fn semaphore_up(sem_id: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::SEMAPHORE_UP, sem_id) }
+}
+
+/// P 操作。
+#[inline]
+pub fn semaphore_down(sem_id: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::SEMAPHORE_DOWN, sem_id) }
+}
+
+/// 创建条件变量，返回条件变量的 ID。
+#[inline]
+pub fn condvar_create() -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::CONDVAR_CREATE, 0) }
+}
+
+/// 唤醒一个等待在条件变量上的线程。
+#[inline]
+pub fn condvar_signal(condvar_id: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::CONDVAR_SIGNAL, condvar_id) }
+}
+
+/// 唤醒所有等待在条件变量上的线程。
+#[inline]
+pub fn condvar_broadcast(condvar_id: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::CONDVAR_BROADCAST, condvar_id) }
+}
+
+/// 释放互斥锁并等待条件变量，被唤醒后重新获取互斥锁。
+#[inline]
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall2(SyscallId::CONDVAR_WAIT, condvar_id, mutex_id) }
+}
+
+/// 同 [`condvar_wait`]，但在单调时钟到达 `abstime` 时超时返回 -ETIMEDOUT（重新获取互斥锁之后）。
+#[inline]
+pub fn condvar_timedwait(condvar_id: usize, mutex_id: usize, abstime: &TimeSpec) -> isize {
+    // SAFETY: abstime 是有效的引用
+    unsafe {
+        syscall3(
+            SyscallId::CONDVAR_TIMEDWAIT,
+            condvar_id,
+            mutex_id,
+            abstime as *const _ as usize,
+        )
+    }
+}
+
+/// 为当前进程开启（1）或关闭（0）死锁检测。
+#[inline]
+pub fn enable_deadlock_detect(is_enable: bool) -> isize {
+    // SAFETY: 系统调用参数是简单的整数值
+    unsafe { syscall1(SyscallId::ENABLE_DEADLOCK_DETECT, is_enable as _) }
+}
+
 /// 这个模块包含调用系统调用的最小封装，用户可以直接使用这些函数调用自定义的系统调用。
 ///
 /// # Safety
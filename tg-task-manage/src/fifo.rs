@@ -0,0 +1,44 @@
+use crate::Schedule;
+use alloc::collections::VecDeque;
+
+/// 先来先服务调度器
+pub struct FifoScheduler<I> {
+    queue: VecDeque<I>,
+}
+
+impl<I> FifoScheduler<I> {
+    /// 新建调度器
+    pub const fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<I> Default for FifoScheduler<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Copy + Ord> FifoScheduler<I> {
+    /// 放到队首，下一次调度时最先取出
+    pub fn add_front(&mut self, id: I) {
+        self.queue.push_front(id);
+    }
+    /// 从就绪队列中移除，返回它是否在队列中
+    pub fn remove(&mut self, id: I) -> bool {
+        let len = self.queue.len();
+        self.queue.retain(|&x| x != id);
+        self.queue.len() != len
+    }
+}
+
+impl<I: Copy + Ord> Schedule<I> for FifoScheduler<I> {
+    fn add(&mut self, id: I) {
+        self.queue.push_back(id);
+    }
+    fn fetch(&mut self) -> Option<I> {
+        self.queue.pop_front()
+    }
+}
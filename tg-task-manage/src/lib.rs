@@ -5,13 +5,23 @@
 
 extern crate alloc;
 
+mod fifo;
 mod id;
 mod manager;
+mod mlfq;
+mod priority;
+mod sched_class;
 mod scheduler;
+mod stride;
 
+pub use fifo::FifoScheduler;
 pub use id::*;
 pub use manager::Manage;
+pub use mlfq::MlfqScheduler;
+pub use priority::PriorityScheduler;
+pub use sched_class::{ClassScheduler, SchedAttr, SchedPolicy};
 pub use scheduler::Schedule;
+pub use stride::{StrideScheduler, BIG_STRIDE, DEFAULT_STRIDE_PRIORITY};
 
 #[cfg(feature = "proc")]
 mod proc_manage;
@@ -27,6 +37,6 @@ mod proc_thread_rel;
 #[cfg(feature = "thread")]
 mod thread_manager;
 #[cfg(feature = "thread")]
-pub use proc_thread_rel::ProcThreadRel;
+pub use proc_thread_rel::{DeadChild, ProcThreadRel, WaitResult, WaitTarget};
 #[cfg(feature = "thread")]
 pub use thread_manager::PThreadManager;
@@ -0,0 +1,77 @@
+use crate::{FifoScheduler, Schedule};
+use alloc::{collections::BTreeMap, vec::Vec};
+
+/// 多级反馈队列调度器
+///
+/// 新线程从最高级（0 级）开始，每用完一个时间片降一级，主动让出 CPU 的线程保持原级别；
+/// 总是调度最高级队列中的线程。每调度 `boost_interval` 次把所有线程提升回最高级，防止饿死。
+pub struct MlfqScheduler<I> {
+    queues: Vec<FifoScheduler<I>>,
+    level: BTreeMap<I, usize>,
+    boost_interval: usize,
+    fetched: usize,
+}
+
+impl<I: Copy + Ord> MlfqScheduler<I> {
+    /// 默认级数
+    pub const DEFAULT_LEVELS: usize = 3;
+    /// 默认提升间隔
+    pub const DEFAULT_BOOST_INTERVAL: usize = 64;
+
+    /// 新建 `levels` 级的调度器，每调度 `boost_interval` 次提升一次
+    pub fn new(levels: usize, boost_interval: usize) -> Self {
+        Self {
+            queues: (0..levels.max(1)).map(|_| FifoScheduler::new()).collect(),
+            level: BTreeMap::new(),
+            boost_interval: boost_interval.max(1),
+            fetched: 0,
+        }
+    }
+    /// 线程所在的级别
+    pub fn level(&self, id: I) -> usize {
+        self.level.get(&id).copied().unwrap_or(0)
+    }
+    /// 移除线程，返回它是否在就绪队列中
+    pub fn remove(&mut self, id: I) -> bool {
+        let level = self.level.remove(&id).unwrap_or(0);
+        self.queues[level].remove(id)
+    }
+
+    /// 把所有线程提升回最高级
+    fn boost(&mut self) {
+        let (top, lower) = self.queues.split_first_mut().unwrap();
+        for queue in lower {
+            while let Some(id) = queue.fetch() {
+                top.add(id);
+            }
+        }
+        self.level.values_mut().for_each(|level| *level = 0);
+    }
+}
+
+impl<I: Copy + Ord> Default for MlfqScheduler<I> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_LEVELS, Self::DEFAULT_BOOST_INTERVAL)
+    }
+}
+
+impl<I: Copy + Ord> Schedule<I> for MlfqScheduler<I> {
+    fn add(&mut self, id: I) {
+        let level = *self.level.entry(id).or_insert(0);
+        self.queues[level].add(id);
+    }
+    fn fetch(&mut self) -> Option<I> {
+        self.fetched += 1;
+        if self.fetched == self.boost_interval {
+            self.fetched = 0;
+            self.boost();
+        }
+        self.queues.iter_mut().find_map(|queue| queue.fetch())
+    }
+    fn add_preempted(&mut self, id: I) {
+        let lowest = self.queues.len() - 1;
+        let level = self.level.entry(id).or_insert(0);
+        *level = (*level + 1).min(lowest);
+        self.queues[*level].add(id);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{FifoScheduler, Schedule};
+use alloc::collections::BTreeMap;
+
+/// 静态优先级调度器
+///
+/// 总是选择优先级最高（数值最大）的线程，相同优先级的线程先来先服务。
+/// 没有设置过优先级的线程优先级为 0。
+pub struct PriorityScheduler<I> {
+    queues: BTreeMap<u8, FifoScheduler<I>>,
+    priority: BTreeMap<I, u8>,
+}
+
+impl<I> PriorityScheduler<I> {
+    /// 新建调度器
+    pub const fn new() -> Self {
+        Self {
+            queues: BTreeMap::new(),
+            priority: BTreeMap::new(),
+        }
+    }
+}
+
+impl<I> Default for PriorityScheduler<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Copy + Ord> PriorityScheduler<I> {
+    /// 线程的优先级
+    pub fn priority(&self, id: I) -> u8 {
+        self.priority.get(&id).copied().unwrap_or(0)
+    }
+    /// 设置线程的优先级，已经在就绪队列中的线程移到新优先级的队尾
+    pub fn set_priority(&mut self, id: I, priority: u8) {
+        let queued = self.dequeue(id);
+        self.priority.insert(id, priority);
+        if queued {
+            self.add(id);
+        }
+    }
+    /// 放到同优先级队列的队首
+    pub fn add_front(&mut self, id: I) {
+        let priority = self.priority(id);
+        self.queues.entry(priority).or_default().add_front(id);
+    }
+    /// 移除线程及其优先级，返回它是否在就绪队列中
+    pub fn remove(&mut self, id: I) -> bool {
+        let queued = self.dequeue(id);
+        self.priority.remove(&id);
+        queued
+    }
+
+    fn dequeue(&mut self, id: I) -> bool {
+        let priority = self.priority(id);
+        self.queues
+            .get_mut(&priority)
+            .is_some_and(|queue| queue.remove(id))
+    }
+}
+
+impl<I: Copy + Ord> Schedule<I> for PriorityScheduler<I> {
+    fn add(&mut self, id: I) {
+        let priority = self.priority(id);
+        self.queues.entry(priority).or_default().add(id);
+    }
+    fn fetch(&mut self) -> Option<I> {
+        while let Some(mut entry) = self.queues.last_entry() {
+            if let Some(id) = entry.get_mut().fetch() {
+                return Some(id);
+            }
+            entry.remove();
+        }
+        None
+    }
+}
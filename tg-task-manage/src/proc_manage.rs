@@ -16,6 +16,8 @@ pub struct PManager<P, MP: Manage<P, ProcId> + Schedule<ProcId>> {
     manager: Option<MP>,
     // 当前正在运行的进程 ID
     current: Option<ProcId>,
+    // init 进程，收养孤儿进程
+    init: Option<ProcId>,
     phantom_data: PhantomData<P>,
 }
 
@@ -26,6 +28,7 @@ impl<P, MP: Manage<P, ProcId> + Schedule<ProcId>> PManager<P, MP> {
             rel_map: BTreeMap::new(),
             manager: None,
             current: None,
+            init: None,
             phantom_data: PhantomData::<P>,
         }
     }
@@ -63,13 +66,23 @@ impl<P, MP: Manage<P, ProcId> + Schedule<ProcId>> PManager<P, MP> {
         if let Some(parent_rel) = self.rel_map.get_mut(&parent_pid) {
             parent_rel.del_child(id, exit_code);
         }
-        // 把当前进程的所有子进程转移到 0 号进程
+        if self.init == Some(id) {
+            self.init = None;
+        }
+        // 把当前进程的所有子进程（包括尚未回收的）转移到 init 进程，init 已结束时不再有父进程
+        let init = self.init.filter(|init| self.rel_map.contains_key(init));
         for i in children {
-            self.rel_map.get_mut(&i).unwrap().parent = ProcId::from_usize(0);
+            self.rel_map.get_mut(&i).unwrap().parent = init.unwrap_or(ProcId::from_usize(usize::MAX));
+            if let Some(init) = init {
+                self.rel_map.get_mut(&init).unwrap().add_child(i);
+            }
+        }
+        if let Some(init) = init {
             self.rel_map
-                .get_mut(&ProcId::from_usize(0))
+                .get_mut(&init)
                 .unwrap()
-                .add_child(i);
+                .dead_children
+                .extend(current_rel.dead_children);
         }
         self.current = None;
     }
@@ -78,7 +91,10 @@ impl<P, MP: Manage<P, ProcId> + Schedule<ProcId>> PManager<P, MP> {
         self.manager.as_mut().unwrap().insert(id, task);
         self.manager.as_mut().unwrap().add(id);
         // 只有当 parent 不是特殊的 usize::MAX（初始进程的父进程）时，才添加到父进程的 children 中
-        if parent.get_usize() != usize::MAX {
+        // 第一个初始进程作为 init
+        if parent.get_usize() == usize::MAX {
+            self.init.get_or_insert(id);
+        } else {
             let parent_relation = self
                 .rel_map
                 .get_mut(&parent)
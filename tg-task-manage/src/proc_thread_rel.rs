@@ -7,25 +7,79 @@ use super::id::{ProcId, ThreadId};
 pub struct ProcThreadRel {
     /// 父进程 Id
     pub parent: ProcId,
+    /// 进程组 Id
+    pub pgid: ProcId,
+    /// 会话 Id
+    pub sid: ProcId,
     /// 子进程列表
     pub children: Vec<ProcId>,
-    /// 已经结束的子进程
-    pub dead_children: Vec<(ProcId, isize)>,
+    /// 已经结束、等待回收的子进程
+    pub dead_children: Vec<DeadChild>,
+    /// 已经停止、尚未报告的子进程及其状态
+    pub stopped_children: Vec<(ProcId, isize)>,
+    /// 从停止中恢复、尚未报告的子进程
+    pub continued_children: Vec<ProcId>,
     /// 线程
     pub threads: Vec<ThreadId>,
     /// 已经结束的线程
     pub dead_threads: Vec<(ThreadId, isize)>,
+    /// 是否收养后代中的孤儿进程（`PR_SET_CHILD_SUBREAPER`）
+    pub subreaper: bool,
+    /// 子进程结束后是否直接回收、不留下僵尸（忽略 SIGCHLD）
+    pub auto_reap: bool,
+}
+
+/// 已经结束、等待回收的子进程
+pub struct DeadChild {
+    /// 进程 Id
+    pub pid: ProcId,
+    /// 结束时所在的进程组
+    pub pgid: ProcId,
+    /// 退出状态，由内核编码
+    pub status: isize,
+}
+
+/// wait 等待的子进程
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaitTarget {
+    /// 任意子进程
+    Any,
+    /// 指定的子进程
+    Pid(ProcId),
+    /// 指定进程组中的子进程
+    Group(ProcId),
+}
+
+/// wait 的结果
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaitResult {
+    /// 没有满足条件的子进程
+    NoChild,
+    /// 有满足条件的子进程，但都没有需要报告的状态变化
+    Pending,
+    /// 子进程结束并被回收
+    Exited(ProcId, isize),
+    /// 子进程停止
+    Stopped(ProcId, isize),
+    /// 子进程从停止中恢复
+    Continued(ProcId),
 }
 
 impl ProcThreadRel {
     /// new/fork 创建进程时使用
-    pub fn new(parent_pid: ProcId) -> Self {
+    pub fn new(parent_pid: ProcId, pgid: ProcId, sid: ProcId) -> Self {
         Self {
             parent: parent_pid,
+            pgid,
+            sid,
             children: Vec::new(),
             dead_children: Vec::new(),
+            stopped_children: Vec::new(),
+            continued_children: Vec::new(),
             threads: Vec::new(),
             dead_threads: Vec::new(),
+            subreaper: false,
+            auto_reap: false,
         }
     }
     /// 添加子进程 Id
@@ -33,54 +87,44 @@ impl ProcThreadRel {
         self.children.push(child_pid);
     }
     /// 子进程结束，子进程 Id 被移入到 dead_children 队列中，等待 wait 系统调用来处理
-    pub fn del_child(&mut self, child_pid: ProcId, exit_code: isize) {
-        let pair = self
-            .children
-            .iter()
-            .enumerate()
-            .find(|(_, &id)| id == child_pid);
-        if let Some((idx, _)) = pair {
-            let dead_child = self.children.remove(idx);
-            self.dead_children.push((dead_child, exit_code));
-        }
-    }
-    /// 等待任意一个结束的子进程，直接弹出 dead_children 队首，如果队列为空，则返回 -2
-    pub fn wait_any_child(&mut self) -> Option<(ProcId, isize)> {
-        if self.dead_children.is_empty() {
-            if self.children.is_empty() {
-                None
-            } else {
-                Some((ProcId::from_usize(-2 as _), -1))
+    ///
+    /// `auto_reap` 时不保留僵尸，返回 false 表示没有留下等待回收的僵尸
+    pub fn del_child(&mut self, child_pid: ProcId, pgid: ProcId, status: isize) -> bool {
+        match self.children.iter().position(|&id| id == child_pid) {
+            Some(idx) => {
+                self.children.remove(idx);
+                self.stopped_children.retain(|&(id, _)| id != child_pid);
+                self.continued_children.retain(|&id| id != child_pid);
+                self.adopt_dead(DeadChild {
+                    pid: child_pid,
+                    pgid,
+                    status,
+                })
             }
-        } else {
-            self.dead_children.pop()
+            None => false,
         }
     }
-    /// 等待特定的子进程
-    pub fn wait_child(&mut self, child_pid: ProcId) -> Option<(ProcId, isize)> {
-        let pair = self
-            .dead_children
-            .iter()
-            .enumerate()
-            .find(|(_, &(id, _))| id == child_pid);
-        if let Some((idx, _)) = pair {
-            // 等待的子进程确已结束
-            Some(self.dead_children.remove(idx))
+    /// 收养一个尚未回收的子进程，`auto_reap` 时直接回收并返回 false
+    pub fn adopt_dead(&mut self, child: DeadChild) -> bool {
+        if self.auto_reap {
+            false
         } else {
-            let pair = self
-                .children
-                .iter()
-                .enumerate()
-                .find(|(_, &id)| id == child_pid);
-            if let Some(_) = pair {
-                // 等待的子进程正在运行
-                Some((ProcId::from_usize(-2 as _), -1))
-            } else {
-                // 等待的子进程不存在
-                None
-            }
+            self.dead_children.push(child);
+            true
         }
     }
+    /// 子进程停止，覆盖之前尚未报告的状态变化
+    pub fn child_stopped(&mut self, child_pid: ProcId, status: isize) {
+        self.continued_children.retain(|&id| id != child_pid);
+        self.stopped_children.retain(|&(id, _)| id != child_pid);
+        self.stopped_children.push((child_pid, status));
+    }
+    /// 子进程从停止中恢复，覆盖之前尚未报告的状态变化
+    pub fn child_continued(&mut self, child_pid: ProcId) {
+        self.stopped_children.retain(|&(id, _)| id != child_pid);
+        self.continued_children.retain(|&id| id != child_pid);
+        self.continued_children.push(child_pid);
+    }
     /// 添加线程
     pub fn add_thread(&mut self, tid: ThreadId) {
         self.threads.push(tid);
@@ -0,0 +1,141 @@
+use crate::{FifoScheduler, MlfqScheduler, PriorityScheduler, Schedule, StrideScheduler};
+use alloc::collections::BTreeMap;
+
+/// 调度策略，与 Linux 的 `SCHED_*` 对应
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SchedPolicy {
+    /// `SCHED_OTHER`，步长调度，份额由 nice 值决定
+    #[default]
+    Normal,
+    /// `SCHED_FIFO`，实时静态优先级，时间片用完也不让出 CPU
+    Fifo,
+    /// `SCHED_RR`，实时静态优先级，同优先级之间按时间片轮转
+    RoundRobin,
+    /// `SCHED_BATCH`，多级反馈队列
+    Batch,
+    /// `SCHED_IDLE`，只在没有其他线程就绪时运行
+    Idle,
+}
+
+impl SchedPolicy {
+    /// 是否为实时策略
+    pub fn is_realtime(self) -> bool {
+        matches!(self, Self::Fifo | Self::RoundRobin)
+    }
+}
+
+/// 线程的调度属性
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct SchedAttr {
+    /// 调度策略
+    pub policy: SchedPolicy,
+    /// 实时优先级，实时策略为 1..=99，其他策略为 0
+    pub rt_priority: u8,
+    /// nice 值，-20..=19
+    pub nice: i8,
+}
+
+/// nice 值对应的权重，与 Linux 的 `sched_prio_to_weight` 相同，nice 每差 1 份额约差 1.25 倍
+const NICE_TO_WEIGHT: [usize; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916, 9548, 7620, 6100, 4904,
+    3906, 3121, 2501, 1991, 1586, 1277, 1024, 820, 655, 526, 423, 335, 272, 215, 172, 137, 110, 87,
+    70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+/// 按调度类组合的调度器
+///
+/// 每个线程按自己的调度策略进入对应的调度类，依次从实时类（静态优先级）、
+/// `Normal`（步长调度）、`Batch`（多级反馈队列）、`Idle`（先来先服务）中选择线程。
+pub struct ClassScheduler<I> {
+    attrs: BTreeMap<I, SchedAttr>,
+    rt: PriorityScheduler<I>,
+    normal: StrideScheduler<I>,
+    batch: MlfqScheduler<I>,
+    idle: FifoScheduler<I>,
+}
+
+impl<I: Copy + Ord> ClassScheduler<I> {
+    /// 新建调度器
+    pub fn new() -> Self {
+        Self {
+            attrs: BTreeMap::new(),
+            rt: PriorityScheduler::new(),
+            normal: StrideScheduler::new(),
+            batch: MlfqScheduler::default(),
+            idle: FifoScheduler::new(),
+        }
+    }
+    /// 线程的调度属性
+    pub fn attr(&self, id: I) -> SchedAttr {
+        self.attrs.get(&id).copied().unwrap_or_default()
+    }
+    /// 设置线程的调度属性，已经在就绪队列中的线程移到新调度类中
+    pub fn set_attr(&mut self, id: I, attr: SchedAttr) {
+        let queued = self.dequeue(id);
+        self.attrs.insert(id, attr);
+        match attr.policy {
+            SchedPolicy::Fifo | SchedPolicy::RoundRobin => {
+                self.rt.set_priority(id, attr.rt_priority)
+            }
+            SchedPolicy::Normal => {
+                let nice = attr.nice.clamp(-20, 19);
+                self.normal
+                    .set_priority(id, NICE_TO_WEIGHT[(nice + 20) as usize]);
+            }
+            SchedPolicy::Batch | SchedPolicy::Idle => {}
+        }
+        if queued {
+            self.add(id);
+        }
+    }
+    /// 移除线程及其调度属性
+    pub fn remove(&mut self, id: I) {
+        self.dequeue(id);
+        self.attrs.remove(&id);
+    }
+
+    /// 从所在调度类中移除，返回它是否在就绪队列中
+    fn dequeue(&mut self, id: I) -> bool {
+        match self.attr(id).policy {
+            SchedPolicy::Fifo | SchedPolicy::RoundRobin => self.rt.remove(id),
+            SchedPolicy::Normal => self.normal.remove(id),
+            SchedPolicy::Batch => self.batch.remove(id),
+            SchedPolicy::Idle => self.idle.remove(id),
+        }
+    }
+}
+
+impl<I: Copy + Ord> Default for ClassScheduler<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Copy + Ord> Schedule<I> for ClassScheduler<I> {
+    fn add(&mut self, id: I) {
+        if !self.attrs.contains_key(&id) {
+            self.set_attr(id, SchedAttr::default());
+        }
+        match self.attr(id).policy {
+            SchedPolicy::Fifo | SchedPolicy::RoundRobin => self.rt.add(id),
+            SchedPolicy::Normal => self.normal.add(id),
+            SchedPolicy::Batch => self.batch.add(id),
+            SchedPolicy::Idle => self.idle.add(id),
+        }
+    }
+    fn fetch(&mut self) -> Option<I> {
+        self.rt
+            .fetch()
+            .or_else(|| self.normal.fetch())
+            .or_else(|| self.batch.fetch())
+            .or_else(|| self.idle.fetch())
+    }
+    fn add_preempted(&mut self, id: I) {
+        match self.attr(id).policy {
+            // 抢占只发生在同一线程的两次调度之间，放回队首即可继续运行
+            SchedPolicy::Fifo => self.rt.add_front(id),
+            SchedPolicy::Batch => self.batch.add_preempted(id),
+            _ => self.add(id),
+        }
+    }
+}
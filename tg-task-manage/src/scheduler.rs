@@ -4,4 +4,8 @@ pub trait Schedule<I: Copy + Ord> {
     fn add(&mut self, id: I);
     /// 出队
     fn fetch(&mut self) -> Option<I>;
+    /// 时间片用完被抢占后入队，默认与 `add` 相同
+    fn add_preempted(&mut self, id: I) {
+        self.add(id);
+    }
 }
@@ -0,0 +1,100 @@
+use crate::Schedule;
+use alloc::collections::{BTreeMap, BTreeSet};
+
+/// 步长的分子，线程每次被调度后行程增加 `BIG_STRIDE / priority`
+pub const BIG_STRIDE: usize = 1 << 20;
+
+/// 线程的默认优先级
+pub const DEFAULT_STRIDE_PRIORITY: usize = 16;
+
+/// 步长调度器
+///
+/// 每个线程的步长与优先级成反比，总是选择行程（pass）最小的线程，
+/// 因此线程获得的 CPU 时间与优先级成正比。
+pub struct StrideScheduler<I> {
+    /// 就绪队列，按 (行程, 入队序号) 排序，行程相同的线程先来先服务
+    ready: BTreeSet<(usize, usize, I)>,
+    info: BTreeMap<I, StrideInfo>,
+    seq: usize,
+    /// 最近一次取出的线程的行程
+    min_pass: usize,
+}
+
+struct StrideInfo {
+    priority: usize,
+    pass: usize,
+    /// 入队时的 (行程, 入队序号)，不在就绪队列中时为 `None`
+    queued: Option<(usize, usize)>,
+}
+
+impl<I> StrideScheduler<I> {
+    /// 新建调度器
+    pub const fn new() -> Self {
+        Self {
+            ready: BTreeSet::new(),
+            info: BTreeMap::new(),
+            seq: 0,
+            min_pass: 0,
+        }
+    }
+}
+
+impl<I> Default for StrideScheduler<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Copy + Ord> StrideScheduler<I> {
+    /// 线程的优先级
+    pub fn priority(&self, id: I) -> usize {
+        self.info
+            .get(&id)
+            .map_or(DEFAULT_STRIDE_PRIORITY, |info| info.priority)
+    }
+    /// 设置线程的优先级，优先级至少为 1，只影响之后的步长
+    pub fn set_priority(&mut self, id: I, priority: usize) {
+        self.entry(id).priority = priority.max(1);
+    }
+    /// 移除线程，返回它是否在就绪队列中
+    pub fn remove(&mut self, id: I) -> bool {
+        match self.info.remove(&id).and_then(|info| info.queued) {
+            Some((pass, seq)) => self.ready.remove(&(pass, seq, id)),
+            None => false,
+        }
+    }
+
+    fn entry(&mut self, id: I) -> &mut StrideInfo {
+        let min_pass = self.min_pass;
+        self.info.entry(id).or_insert(StrideInfo {
+            priority: DEFAULT_STRIDE_PRIORITY,
+            pass: min_pass,
+            queued: None,
+        })
+    }
+}
+
+impl<I: Copy + Ord> Schedule<I> for StrideScheduler<I> {
+    fn add(&mut self, id: I) {
+        let min_pass = self.min_pass;
+        let seq = self.seq;
+        let info = self.entry(id);
+        if info.queued.is_some() {
+            return;
+        }
+        // 长时间阻塞的线程行程落后太多，唤醒后会一直占用 CPU，从当前的最小行程开始
+        info.pass = info.pass.max(min_pass);
+        info.queued = Some((info.pass, seq));
+        let pass = info.pass;
+        self.ready.insert((pass, seq, id));
+        self.seq += 1;
+    }
+    fn fetch(&mut self) -> Option<I> {
+        let (pass, _, id) = self.ready.pop_first()?;
+        let info = self.info.get_mut(&id).unwrap();
+        info.pass += BIG_STRIDE / info.priority;
+        info.queued = None;
+        self.min_pass = pass;
+        Some(id)
+    }
+}
@@ -6,7 +6,7 @@ use crate::ThreadId;
 use super::id::ProcId;
 use super::manager::Manage;
 use super::scheduler::Schedule;
-use super::ProcThreadRel;
+use super::{ProcThreadRel, WaitResult, WaitTarget};
 use core::marker::PhantomData;
 
 #[cfg(feature = "thread")]
@@ -24,6 +24,10 @@ pub struct PThreadManager<P, T, MT: Manage<T, ThreadId> + Schedule<ThreadId>, MP
     manager: Option<MT>,
     // 当前正在运行的线程 ID
     current: Option<ThreadId>,
+    // init 进程，收养没有 subreaper 祖先的孤儿进程
+    init: Option<ProcId>,
+    // 结束后没有留下僵尸、已被直接回收的进程
+    reaped: Vec<ProcId>,
     phantom_t: PhantomData<T>,
     phantom_p: PhantomData<P>,
 }
@@ -39,27 +43,36 @@ impl<P, T, MT: Manage<T, ThreadId> + Schedule<ThreadId>, MP: Manage<P, ProcId>>
             tid2pid: BTreeMap::new(),
             manager: None,
             current: None,
+            init: None,
+            reaped: Vec::new(),
             phantom_t: PhantomData::<T>,
             phantom_p: PhantomData::<P>,
         }
     }
-    /// 找到下一个进程
+    /// 找到下一个线程
+    ///
+    /// 调度队列中可能残留已经被删除的线程（例如随进程一起结束的线程），直接跳过
     pub fn find_next(&mut self) -> Option<&mut T> {
-        if let Some(id) = self.manager.as_mut().unwrap().fetch() {
-            if let Some(task) = self.manager.as_mut().unwrap().get_mut(id) {
+        while let Some(id) = self.manager.as_mut().unwrap().fetch() {
+            if self.manager.as_mut().unwrap().get_mut(id).is_some() {
                 self.current = Some(id);
-                Some(task)
-            } else {
-                None
+                return self.manager.as_mut().unwrap().get_mut(id);
             }
-        } else {
-            None
         }
+        None
     }
     /// 设置 manager
     pub fn set_manager(&mut self, manager: MT) {
         self.manager = Some(manager);
     }
+    /// 获取 manager
+    pub fn manager(&mut self) -> &mut MT {
+        self.manager.as_mut().unwrap()
+    }
+    /// 获取 proc_manager
+    pub fn proc_manager(&mut self) -> &mut MP {
+        self.proc_manager.as_mut().unwrap()
+    }
     /// 设置 proc_manager
     pub fn set_proc_manager(&mut self, proc_manager: MP) {
         self.proc_manager = Some(proc_manager);
@@ -71,6 +84,13 @@ impl<P, T, MT: Manage<T, ThreadId> + Schedule<ThreadId>, MP: Manage<P, ProcId>>
             self.current = None;
         }
     }
+    /// 当前线程时间片用完，被抢占后重新入队
+    pub fn make_current_preempted(&mut self) {
+        if let Some(id) = self.current {
+            self.manager.as_mut().unwrap().add_preempted(id);
+            self.current = None;
+        }
+    }
     /// 结束当前线程
     pub fn make_current_exited(&mut self, exit_code: isize) {
         if let Some(id) = self.current {
@@ -91,6 +111,37 @@ impl<P, T, MT: Manage<T, ThreadId> + Schedule<ThreadId>, MP: Manage<P, ProcId>>
             self.current = None;
         }
     }
+    /// 结束当前线程所属进程中的所有线程，并结束该进程（`exit_group`）
+    pub fn make_current_group_exited(&mut self, exit_code: isize) {
+        if let Some(id) = self.current {
+            let pid = *self.tid2pid.get(&id).unwrap();
+            let threads = core::mem::take(&mut self.rel_map.get_mut(&pid).unwrap().threads);
+            for tid in threads {
+                self.manager.as_mut().unwrap().delete(tid);
+                self.tid2pid.remove(&tid);
+            }
+            self.del_proc(pid, exit_code);
+            self.current = None;
+        }
+    }
+    /// 结束当前进程中除当前线程以外的所有线程（`execve`）
+    pub fn make_others_exited(&mut self) {
+        if let Some(id) = self.current {
+            let pid = *self.tid2pid.get(&id).unwrap();
+            let threads = core::mem::take(&mut self.rel_map.get_mut(&pid).unwrap().threads);
+            for tid in threads {
+                if tid != id {
+                    self.manager.as_mut().unwrap().delete(tid);
+                    self.tid2pid.remove(&tid);
+                }
+            }
+            self.rel_map.get_mut(&pid).unwrap().threads.push(id);
+        }
+    }
+    /// 当前线程的 Id
+    pub fn current_tid(&self) -> Option<ThreadId> {
+        self.current
+    }
     /// 让当前线程阻塞
     pub fn make_current_blocked(&mut self) {
         if let Some(_) = self.current {
@@ -121,48 +172,198 @@ impl<P, T, MT: Manage<T, ThreadId> + Schedule<ThreadId>, MP: Manage<P, ProcId>>
     pub fn get_task(&mut self, id: ThreadId) -> Option<&mut T> {
         self.manager.as_mut().unwrap().get_mut(id)
     }
-    /// 添加进程
+    /// 添加进程，新进程与父进程在同一个进程组和会话，并继承父进程是否自动回收子进程；
+    /// 没有父进程时自成一组和一个会话，第一个这样的进程作为 init
     pub fn add_proc(&mut self, id: ProcId, proc: P, parent: ProcId) {
         self.proc_manager.as_mut().unwrap().insert(id, proc);
-        if let Some(parent_rel) = self.rel_map.get_mut(&parent) {
-            parent_rel.add_child(id);
-        }
-        self.rel_map.insert(id, ProcThreadRel::new(parent));
+        let rel = match self.rel_map.get_mut(&parent) {
+            Some(parent_rel) => {
+                parent_rel.add_child(id);
+                let mut rel = ProcThreadRel::new(parent, parent_rel.pgid, parent_rel.sid);
+                rel.auto_reap = parent_rel.auto_reap;
+                rel
+            }
+            None => {
+                self.init.get_or_insert(id);
+                ProcThreadRel::new(parent, id, id)
+            }
+        };
+        self.rel_map.insert(id, rel);
     }
     /// 查询进程
     pub fn get_proc(&mut self, id: ProcId) -> Option<&mut P> {
         self.proc_manager.as_mut().unwrap().get_mut(id)
     }
+    /// 进程所在的进程组
+    pub fn get_pgid(&self, id: ProcId) -> Option<ProcId> {
+        self.rel_map.get(&id).map(|rel| rel.pgid)
+    }
+    /// 进程所在的会话
+    pub fn get_sid(&self, id: ProcId) -> Option<ProcId> {
+        self.rel_map.get(&id).map(|rel| rel.sid)
+    }
+    /// 父进程，没有父进程时返回 `None`
+    pub fn get_parent(&self, id: ProcId) -> Option<ProcId> {
+        let parent = self.rel_map.get(&id)?.parent;
+        self.rel_map.contains_key(&parent).then_some(parent)
+    }
+    /// 把进程移入进程组 `pgid`
+    pub fn set_pgid(&mut self, id: ProcId, pgid: ProcId) {
+        if let Some(rel) = self.rel_map.get_mut(&id) {
+            rel.pgid = pgid;
+        }
+    }
+    /// 进程新建一个会话，成为新会话和新进程组的首进程
+    pub fn set_sid(&mut self, id: ProcId) {
+        if let Some(rel) = self.rel_map.get_mut(&id) {
+            rel.pgid = id;
+            rel.sid = id;
+        }
+    }
+    /// init 进程，已结束时返回 `None`
+    pub fn init_proc(&self) -> Option<ProcId> {
+        self.init.filter(|init| self.rel_map.contains_key(init))
+    }
+    /// 所有进程
+    pub fn procs(&self) -> impl Iterator<Item = ProcId> + '_ {
+        self.rel_map.keys().copied()
+    }
+    /// 进程组 `pgid` 中的所有进程
+    pub fn procs_in_group(&self, pgid: ProcId) -> impl Iterator<Item = ProcId> + '_ {
+        self.rel_map
+            .iter()
+            .filter(move |(_, rel)| rel.pgid == pgid)
+            .map(|(&id, _)| id)
+    }
     /// 结束当前进程
     pub fn del_proc(&mut self, id: ProcId, exit_code: isize) {
         // 删除进程实体
         self.proc_manager.as_mut().unwrap().delete(id);
-        // 进程结束时维护父子关系，进程删除后，所有的子进程交给 0 号进程来维护
         let current_rel = self.rel_map.remove(&id).unwrap();
         let parent_pid = current_rel.parent;
-        let children = current_rel.children;
-        // 从父进程中删除当前进程
-        if let Some(parent_rel) = self.rel_map.get_mut(&parent_pid) {
-            parent_rel.del_child(id, exit_code);
-        }
-        // 把当前进程的所有子进程转移到 0 号进程
-        for i in children {
-            self.rel_map.get_mut(&i).unwrap().parent = ProcId::from_usize(0);
-            self.rel_map
-                .get_mut(&ProcId::from_usize(0))
-                .unwrap()
-                .add_child(i);
-        }
-    }
-    /// wait 系统调用，返回结束的子进程 id 和 exit_code，正在运行的子进程不返回 None，返回 (-2, -1)
-    pub fn wait(&mut self, child_pid: ProcId) -> Option<(ProcId, isize)> {
+        if self.init == Some(id) {
+            self.init = None;
+        }
+        // 从父进程中删除当前进程，没有父进程或父进程不保留僵尸时直接回收
+        let zombie = self
+            .rel_map
+            .get_mut(&parent_pid)
+            .is_some_and(|parent_rel| parent_rel.del_child(id, current_rel.pgid, exit_code));
+        if !zombie {
+            self.reaped.push(id);
+        }
+        // 子进程（包括尚未回收的）交给最近的 subreaper 祖先，没有时交给 init
+        let reaper = self.find_reaper(parent_pid);
+        for child in current_rel.children {
+            let child_rel = self.rel_map.get_mut(&child).unwrap();
+            match reaper {
+                Some(reaper) => {
+                    child_rel.parent = reaper;
+                    self.rel_map.get_mut(&reaper).unwrap().add_child(child);
+                }
+                // init 也已结束，孤儿进程结束后直接回收
+                None => child_rel.parent = ProcId::from_usize(usize::MAX),
+            }
+        }
+        for child in current_rel.dead_children {
+            let pid = child.pid;
+            let zombie = reaper.is_some_and(|reaper| {
+                self.rel_map.get_mut(&reaper).unwrap().adopt_dead(child)
+            });
+            if !zombie {
+                self.reaped.push(pid);
+            }
+        }
+    }
+    /// 从 `pid` 开始向上查找第一个 subreaper，找不到时返回仍在运行的 init
+    fn find_reaper(&self, mut pid: ProcId) -> Option<ProcId> {
+        while let Some(rel) = self.rel_map.get(&pid) {
+            if rel.subreaper {
+                return Some(pid);
+            }
+            pid = rel.parent;
+        }
+        self.init_proc()
+    }
+    /// 设置进程是否收养后代中的孤儿进程
+    pub fn set_subreaper(&mut self, id: ProcId, subreaper: bool) {
+        if let Some(rel) = self.rel_map.get_mut(&id) {
+            rel.subreaper = subreaper;
+        }
+    }
+    /// 进程是否收养后代中的孤儿进程
+    pub fn is_subreaper(&self, id: ProcId) -> bool {
+        self.rel_map.get(&id).is_some_and(|rel| rel.subreaper)
+    }
+    /// 设置进程的子进程结束后是否直接回收，已有的僵尸不受影响
+    pub fn set_auto_reap(&mut self, id: ProcId, auto_reap: bool) {
+        if let Some(rel) = self.rel_map.get_mut(&id) {
+            rel.auto_reap = auto_reap;
+        }
+    }
+    /// 取出结束后已被直接回收的进程，调用者据此释放为 wait 保留的资源
+    pub fn take_reaped(&mut self) -> Vec<ProcId> {
+        core::mem::take(&mut self.reaped)
+    }
+    /// 子进程停止，记录在父进程中等待 wait 报告
+    pub fn child_stopped(&mut self, id: ProcId, status: isize) {
+        if let Some(parent) = self.rel_map.get(&id).map(|rel| rel.parent) {
+            if let Some(parent_rel) = self.rel_map.get_mut(&parent) {
+                parent_rel.child_stopped(id, status);
+            }
+        }
+    }
+    /// 子进程从停止中恢复，记录在父进程中等待 wait 报告
+    pub fn child_continued(&mut self, id: ProcId) {
+        if let Some(parent) = self.rel_map.get(&id).map(|rel| rel.parent) {
+            if let Some(parent_rel) = self.rel_map.get_mut(&parent) {
+                parent_rel.child_continued(id);
+            }
+        }
+    }
+    /// wait 系统调用，按结束、停止（`stopped`）、恢复（`continued`）的顺序查找满足 `target` 的子进程状态变化
+    ///
+    /// 结束的子进程在返回后被回收
+    pub fn wait(&mut self, target: WaitTarget, stopped: bool, continued: bool) -> WaitResult {
         let id = self.current.unwrap();
-        let pid = self.tid2pid.get(&id).unwrap();
-        let current_rel = self.rel_map.get_mut(pid).unwrap();
-        if child_pid.get_usize() == usize::MAX {
-            current_rel.wait_any_child()
+        let pid = *self.tid2pid.get(&id).unwrap();
+        let rel_map = &self.rel_map;
+        let matches = |child: ProcId, pgid: Option<ProcId>| match target {
+            WaitTarget::Any => true,
+            WaitTarget::Pid(target) => child == target,
+            WaitTarget::Group(target) => pgid == Some(target),
+        };
+        let pgid = |child: ProcId| rel_map.get(&child).map(|rel| rel.pgid);
+        let current_rel = rel_map.get(&pid).unwrap();
+        let dead = current_rel
+            .dead_children
+            .iter()
+            .position(|child| matches(child.pid, Some(child.pgid)));
+        let stop = current_rel
+            .stopped_children
+            .iter()
+            .position(|&(child, _)| stopped && matches(child, pgid(child)));
+        let cont = current_rel
+            .continued_children
+            .iter()
+            .position(|&child| continued && matches(child, pgid(child)));
+        let pending = current_rel
+            .children
+            .iter()
+            .any(|&child| matches(child, pgid(child)));
+        let current_rel = self.rel_map.get_mut(&pid).unwrap();
+        if let Some(idx) = dead {
+            let child = current_rel.dead_children.remove(idx);
+            WaitResult::Exited(child.pid, child.status)
+        } else if let Some(idx) = stop {
+            let (child, status) = current_rel.stopped_children.remove(idx);
+            WaitResult::Stopped(child, status)
+        } else if let Some(idx) = cont {
+            WaitResult::Continued(current_rel.continued_children.remove(idx))
+        } else if pending {
+            WaitResult::Pending
         } else {
-            current_rel.wait_child(child_pid)
+            WaitResult::NoChild
         }
     }
     /// wait_tid 系统调用